@@ -0,0 +1,49 @@
+use anyhow::Result;
+use std::ops::Range;
+
+use super::Chunker;
+
+/// Consecutive pieces of `size` characters, cut wherever the count runs out.
+pub struct FixedSize {
+    pub size: usize,
+}
+
+impl Chunker for FixedSize {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let mut spans = Vec::new();
+        let mut start = 0;
+        for (count, (offset, _)) in text.char_indices().enumerate() {
+            if count > 0 && count % self.size == 0 {
+                spans.push(start..offset);
+                start = offset;
+            }
+        }
+        if start < text.len() {
+            spans.push(start..text.len());
+        }
+        Ok(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Document;
+
+    #[test]
+    fn cuts_every_size_characters_on_char_boundaries() {
+        let text = "héllo wörld ✓✓ 日本語";
+        let spans = FixedSize { size: 3 }.spans(text).unwrap();
+        let pieces: Vec<&str> = spans.iter().map(|span| &text[span.clone()]).collect();
+        assert_eq!(pieces, ["hél", "lo ", "wör", "ld ", "✓✓ ", "日本語"]);
+        assert_eq!(pieces.concat(), text);
+    }
+
+    #[test]
+    fn drops_whitespace_only_chunks() {
+        let chunks = FixedSize { size: 3 }.chunk(&Document::new("test.txt", "abc   déf")).unwrap();
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(texts, ["abc", "déf"]);
+        assert_eq!(chunks.iter().map(|chunk| chunk.index).collect::<Vec<_>>(), [0, 1]);
+    }
+}
@@ -0,0 +1,444 @@
+//! Splitting documents into the chunks that become points.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, ops::Range, path::PathBuf, str::FromStr};
+use uuid::Uuid;
+
+use crate::store::PointId;
+
+mod fixed;
+mod recursive;
+mod sentence;
+#[cfg(feature = "tokenizer")]
+mod tokens;
+mod window;
+
+pub use fixed::FixedSize;
+pub use recursive::Recursive;
+pub use sentence::Sentences;
+#[cfg(feature = "tokenizer")]
+pub use tokens::TokenChunker;
+pub use window::SlidingWindow;
+
+/// Namespace of the UUIDv5 point ids generated by [`stable_ids`].
+const POINT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x5b0e_93c1_7d2a_4f86_a1c4_38e2_9d67_0b15);
+
+/// A source text with the metadata shared by all of its chunks.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// File or other origin of the text.
+    pub source: String,
+    /// The whole text, front matter included, so that offsets point into the original file.
+    pub content: String,
+    /// Byte offset of the body, after any front matter.
+    pub body_offset: usize,
+    /// Custom key/values copied into the payload of every chunk.
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Byte offsets at which each line of `content` starts.
+    line_starts: Vec<usize>,
+    /// Markdown headings of the body as `(byte offset, level, title)`.
+    headings: Vec<(usize, usize, String)>,
+}
+
+/// A piece of a source document, embedded as one point.
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pub text: String,
+    /// File or other origin of the text.
+    pub source: String,
+    /// Position of the chunk among the chunks of its source.
+    pub index: usize,
+    /// Byte offset of the chunk in its source.
+    pub byte_offset: usize,
+    /// Lines of the source the chunk starts and ends on, counting from 1.
+    pub lines: (usize, usize),
+    /// Titles of the Markdown headings the chunk is under, outermost first.
+    pub heading_path: Vec<String>,
+    /// Custom key/values of the document.
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Length in tokens of the embedding model, special tokens included, when a chunker
+    /// measured it.
+    pub tokens: Option<usize>,
+}
+
+impl Document {
+    /// A document without front matter.
+    pub fn new(source: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::with_body(source.into(), content.into(), 0, serde_json::Map::new())
+    }
+
+    /// A document whose front matter, if any, becomes its metadata: flat `key: value` lines
+    /// between `---` fences, or TOML between `+++` fences.
+    pub fn parse(source: impl Into<String>, content: impl Into<String>) -> Result<Self> {
+        let (source, content) = (source.into(), content.into());
+        let (metadata, body_offset) = front_matter(&content)
+            .with_context(|| format!("Invalid front matter in {}", source))?;
+        Ok(Self::with_body(source, content, body_offset, metadata))
+    }
+
+    fn with_body(source: String, content: String, body_offset: usize, metadata: serde_json::Map<String, serde_json::Value>) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+        let mut headings = Vec::new();
+        let mut offset = body_offset;
+        for line in content[body_offset..].split_inclusive('\n') {
+            let level = line.bytes().take_while(|&byte| byte == b'#').count();
+            if (1..=6).contains(&level) && line[level..].starts_with([' ', '\t']) {
+                headings.push((offset, level, line[level..].trim().trim_end_matches('#').trim_end().to_string()));
+            }
+            offset += line.len();
+        }
+        Self { source, content, body_offset, metadata, line_starts, headings }
+    }
+
+    /// The text after the front matter.
+    pub fn body(&self) -> &str {
+        &self.content[self.body_offset..]
+    }
+
+    /// The chunk at byte `span` of the [body](Document::body).
+    pub fn chunk(&self, index: usize, span: Range<usize>) -> Chunk {
+        let start = self.body_offset + span.start;
+        let end = self.body_offset + span.end;
+        let line = |offset: usize| self.line_starts.partition_point(|&line_start| line_start <= offset);
+
+        let mut heading_path: Vec<(usize, &str)> = Vec::new();
+        for (_, level, title) in self.headings.iter().take_while(|(offset, _, _)| *offset <= start) {
+            heading_path.retain(|&(outer, _)| outer < *level);
+            heading_path.push((*level, title.as_str()));
+        }
+
+        Chunk {
+            text: self.content[start..end].to_string(),
+            source: self.source.clone(),
+            index,
+            byte_offset: start,
+            lines: (line(start), line(end.saturating_sub(1).max(start))),
+            heading_path: heading_path.into_iter().map(|(_, title)| title.to_string()).collect(),
+            metadata: self.metadata.clone(),
+            tokens: None,
+        }
+    }
+}
+
+impl Chunk {
+    /// SHA-256 of the text, hex encoded.
+    pub fn content_hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.text.as_bytes()))
+    }
+
+    /// Number of words and punctuation marks, which is how BERT-style tokenizers split text
+    /// before breaking words into pieces, so a lower bound on the model's token count.
+    pub fn word_count(&self) -> usize {
+        let mut count = 0;
+        let mut in_word = false;
+        for c in self.text.chars() {
+            if c.is_alphanumeric() {
+                count += usize::from(!in_word);
+                in_word = true;
+            } else {
+                count += usize::from(!c.is_whitespace());
+                in_word = false;
+            }
+        }
+        count
+    }
+
+    /// Payload stored with the point: the custom metadata, overridden by the text, its hash and
+    /// where it came from. `token_count` is only present when a chunker measured
+    /// [`tokens`](Chunk::tokens).
+    pub fn payload(&self) -> HashMap<String, serde_json::Value> {
+        let mut payload: HashMap<String, serde_json::Value> = self.metadata.clone().into_iter().collect();
+        payload.extend([
+            ("text".to_string(), self.text.clone().into()),
+            ("content_hash".to_string(), self.content_hash().into()),
+            ("source".to_string(), self.source.clone().into()),
+            ("chunk_index".to_string(), self.index.into()),
+            ("byte_offset".to_string(), self.byte_offset.into()),
+            ("byte_end".to_string(), (self.byte_offset + self.text.len()).into()),
+            ("line_start".to_string(), self.lines.0.into()),
+            ("line_end".to_string(), self.lines.1.into()),
+            ("heading_path".to_string(), self.heading_path.clone().into()),
+            ("char_count".to_string(), char_count(&self.text).into()),
+            ("word_count".to_string(), self.word_count().into()),
+        ]);
+        if let Some(tokens) = self.tokens {
+            payload.insert("token_count".to_string(), tokens.into());
+        }
+        payload
+    }
+}
+
+/// A way of cutting a text into pieces small enough to embed.
+pub trait Chunker: Send + Sync {
+    /// Byte ranges of `text` to turn into chunks, in order. They may overlap and need not be
+    /// trimmed.
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>>;
+
+    /// Chunks of the body of `document`, with surrounding whitespace trimmed and empty chunks
+    /// dropped.
+    fn chunk(&self, document: &Document) -> Result<Vec<Chunk>> {
+        let body = document.body();
+        Ok(self.spans(body)?
+            .into_iter()
+            .filter_map(|span| trim_span(body, span))
+            .enumerate()
+            .map(|(index, span)| document.chunk(index, span))
+            .collect())
+    }
+
+    /// Longest chunk, in tokens, the embedding model reads without truncating it, for chunkers
+    /// that measure [`tokens`](Chunk::tokens).
+    fn max_tokens(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Which [`Chunker`] to use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    /// One chunk per paragraph, however long.
+    #[default]
+    Paragraph,
+    /// Consecutive pieces of exactly `size` characters.
+    Fixed,
+    /// Whole sentences packed into chunks of up to `size` characters.
+    Sentence,
+    /// Split on paragraphs, then lines, sentences and words until pieces fit in `size`
+    /// characters, merging neighbours back together while they fit.
+    Recursive,
+    /// Windows of whole words up to `size` characters, each sharing up to `overlap` characters
+    /// with the previous one.
+    Window,
+    /// Windows of whole words up to `size` tokens of the embedding model's tokenizer, each
+    /// sharing up to `overlap` tokens with the previous one. Requires the `tokenizer` feature.
+    Tokens,
+}
+
+impl FromStr for ChunkStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "paragraph" | "paragraphs" => Ok(ChunkStrategy::Paragraph),
+            "fixed" => Ok(ChunkStrategy::Fixed),
+            "sentence" | "sentences" => Ok(ChunkStrategy::Sentence),
+            "recursive" => Ok(ChunkStrategy::Recursive),
+            "window" | "sliding-window" => Ok(ChunkStrategy::Window),
+            "tokens" | "token" => Ok(ChunkStrategy::Tokens),
+            _ => anyhow::bail!("Unknown chunker {}, expected paragraph, fixed, sentence, recursive, window or tokens", s),
+        }
+    }
+}
+
+pub const DEFAULT_CHUNK_SIZE: usize = 800;
+pub const DEFAULT_CHUNK_OVERLAP: usize = 100;
+/// The longest input sentence-transformers models such as all-MiniLM-L6-v2 read.
+pub const DEFAULT_CHUNK_TOKENS: usize = 256;
+pub const DEFAULT_TOKEN_OVERLAP: usize = 32;
+
+/// Which chunker to use and how large its chunks are.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkerConfig {
+    pub strategy: ChunkStrategy,
+    /// Maximum chunk length, in tokens for [`ChunkStrategy::Tokens`] and characters otherwise;
+    /// ignored by [`ChunkStrategy::Paragraph`].
+    pub size: Option<usize>,
+    /// Length shared by consecutive windows of [`ChunkStrategy::Window`] and [`ChunkStrategy::Tokens`].
+    pub overlap: Option<usize>,
+    /// `tokenizer.json` of the embedding model, for [`ChunkStrategy::Tokens`].
+    pub tokenizer: Option<PathBuf>,
+    /// Longest input of the embedding model in tokens, for [`ChunkStrategy::Tokens`]; read from
+    /// the model's files when unset, see `TokenChunker::from_file`.
+    pub max_tokens: Option<usize>,
+}
+
+impl ChunkerConfig {
+    pub fn build(&self) -> Result<Box<dyn Chunker>> {
+        let (size, overlap) = match self.strategy {
+            ChunkStrategy::Tokens => (self.size.unwrap_or(DEFAULT_CHUNK_TOKENS), self.overlap.unwrap_or(DEFAULT_TOKEN_OVERLAP)),
+            _ => (self.size.unwrap_or(DEFAULT_CHUNK_SIZE), self.overlap.unwrap_or(DEFAULT_CHUNK_OVERLAP)),
+        };
+        anyhow::ensure!(size > 0, "Chunk size must be positive");
+        if matches!(self.strategy, ChunkStrategy::Window | ChunkStrategy::Tokens) {
+            anyhow::ensure!(overlap < size, "Chunk overlap {} must be smaller than the chunk size {}", overlap, size);
+        }
+
+        Ok(match self.strategy {
+            ChunkStrategy::Paragraph => Box::new(Paragraphs),
+            ChunkStrategy::Fixed => Box::new(FixedSize { size }),
+            ChunkStrategy::Sentence => Box::new(Sentences { size }),
+            ChunkStrategy::Recursive => Box::new(Recursive::new(size)),
+            ChunkStrategy::Window => Box::new(SlidingWindow { size, overlap }),
+            #[cfg(feature = "tokenizer")]
+            ChunkStrategy::Tokens => {
+                let path = self.tokenizer.as_ref().context("The tokens chunker needs the model's tokenizer.json")?;
+                let chunker = TokenChunker::from_file(path, size, overlap)?;
+                Box::new(match self.max_tokens {
+                    Some(max_tokens) => chunker.with_max_length(max_tokens),
+                    None => chunker,
+                })
+            }
+            #[cfg(not(feature = "tokenizer"))]
+            ChunkStrategy::Tokens => anyhow::bail!("The tokens chunker requires building with --features tokenizer"),
+        })
+    }
+}
+
+/// Splits on blank lines, one chunk per paragraph.
+pub struct Paragraphs;
+
+impl Chunker for Paragraphs {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let mut start = 0;
+        Ok(text.split("\n\n")
+            .map(|paragraph| {
+                let span = start..start + paragraph.len();
+                start = span.end + 2;
+                span
+            })
+            .collect())
+    }
+}
+
+/// Splits the body of `document` on blank lines, one chunk per paragraph.
+pub fn paragraphs(document: &Document) -> Result<Vec<Chunk>> {
+    Paragraphs.chunk(document)
+}
+
+/// `span` without leading and trailing whitespace, or `None` if nothing else is left.
+fn trim_span(text: &str, span: Range<usize>) -> Option<Range<usize>> {
+    let piece = &text[span.clone()];
+    let start = span.start + (piece.len() - piece.trim_start().len());
+    let end = span.start + piece.trim_end().len();
+    (start < end).then_some(start..end)
+}
+
+fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// A metadata value given as text: JSON if it parses as JSON, so that `2023` and `true` keep
+/// their types, and a string otherwise.
+pub fn metadata_value(text: &str) -> serde_json::Value {
+    serde_json::from_str(text).unwrap_or_else(|_| text.into())
+}
+
+/// Parses the front matter at the start of `content`, returning it and the offset of the body.
+fn front_matter(content: &str) -> Result<(serde_json::Map<String, serde_json::Value>, usize)> {
+    let Some(fence) = ["---", "+++"].into_iter().find(|fence| content.lines().next() == Some(*fence)) else {
+        return Ok((serde_json::Map::new(), 0));
+    };
+
+    let mut lines = content.split_inclusive('\n');
+    let header_start = lines.next().map_or(0, str::len);
+    let mut offset = header_start;
+    for line in lines {
+        if line.trim_end_matches(['\r', '\n']) == fence {
+            let header = &content[header_start..offset];
+            let metadata = if fence == "+++" { toml_front_matter(header)? } else { flat_front_matter(header)? };
+            return Ok((metadata, offset + line.len()));
+        }
+        offset += line.len();
+    }
+    anyhow::bail!("Missing closing {}", fence)
+}
+
+fn flat_front_matter(header: &str) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let mut metadata = serde_json::Map::new();
+    for line in header.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (key, value) = line.split_once(':')
+            .with_context(|| format!("Expected key: value, found {}", line))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+            Some(quoted) => quoted.into(),
+            None => metadata_value(value),
+        };
+        metadata.insert(key.trim().to_string(), value);
+    }
+    Ok(metadata)
+}
+
+fn toml_front_matter(header: &str) -> Result<serde_json::Map<String, serde_json::Value>> {
+    fn json(value: toml::Value) -> serde_json::Value {
+        match value {
+            toml::Value::String(value) => value.into(),
+            toml::Value::Integer(value) => value.into(),
+            toml::Value::Float(value) => value.into(),
+            toml::Value::Boolean(value) => value.into(),
+            toml::Value::Datetime(value) => value.to_string().into(),
+            toml::Value::Array(values) => values.into_iter().map(json).collect(),
+            toml::Value::Table(table) => table.into_iter().map(|(key, value)| (key, json(value))).collect(),
+        }
+    }
+
+    let table: toml::Table = toml::from_str(header)?;
+    Ok(table.into_iter().map(|(key, value)| (key, json(value))).collect())
+}
+
+/// UUIDv5 ids derived from the source and text of each chunk, so that editing one part of a
+/// document leaves the ids of all other chunks unchanged. Chunks repeating an earlier text of
+/// the same source are told apart by how many times it occurred before.
+pub fn stable_ids(chunks: &[Chunk]) -> Vec<PointId> {
+    let mut occurrences: HashMap<(&str, &str), usize> = HashMap::new();
+    chunks.iter()
+        .map(|chunk| {
+            let occurrence = occurrences.entry((chunk.source.as_str(), chunk.text.as_str())).or_default();
+            let name = format!("{}\0{}\0{}", chunk.source, occurrence, chunk.text);
+            *occurrence += 1;
+            PointId::Uuid(Uuid::new_v5(&POINT_ID_NAMESPACE, name.as_bytes()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_token_counts_only_when_measured() {
+        let document = Document::new("notes.md", "# Title\n\nHello, world!\n");
+        let mut chunk = document.chunk(0, 9..22);
+        assert_eq!(chunk.text, "Hello, world!");
+        assert_eq!(chunk.word_count(), 4);
+
+        let payload = chunk.payload();
+        assert_eq!(payload["word_count"], 4);
+        assert_eq!(payload["char_count"], 13);
+        assert_eq!(payload["heading_path"], serde_json::json!(["Title"]));
+        assert!(!payload.contains_key("token_count"));
+
+        chunk.tokens = Some(6);
+        assert_eq!(chunk.payload()["token_count"], 6);
+    }
+
+    #[test]
+    fn stable_ids_survive_insertions() {
+        let original = include_str!("../reg-all.txt");
+        let before = stable_ids(&paragraphs(&Document::new("reg-all.txt", original)).unwrap());
+        let edited = format!("A new paragraph at the top.\n\n{}", original);
+        let after = stable_ids(&paragraphs(&Document::new("reg-all.txt", edited)).unwrap());
+
+        assert!(before.len() > 1);
+        assert_eq!(after.len(), before.len() + 1);
+        assert!(!before.contains(&after[0]));
+        assert_eq!(after[1..], before[..]);
+    }
+
+    #[test]
+    fn stable_ids_tell_repeats_and_sources_apart() {
+        let text = "Same words.\n\nOther words.\n\nSame words.";
+        let ids = stable_ids(&paragraphs(&Document::new("a.txt", text)).unwrap());
+        assert_eq!(ids.len(), 3);
+        assert_ne!(ids[0], ids[2]);
+        assert_ne!(ids[0], ids[1]);
+
+        let elsewhere = stable_ids(&paragraphs(&Document::new("b.txt", text)).unwrap());
+        assert!(elsewhere.iter().all(|id| !ids.contains(id)));
+        assert_eq!(stable_ids(&paragraphs(&Document::new("a.txt", text)).unwrap()), ids);
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::ops::Range;
+
+use super::{char_count, Chunker, FixedSize};
+
+/// Splits text on the first of `separators` it contains, merges neighbouring pieces back
+/// together while they fit in `size` characters, and splits pieces that are still too long
+/// on the next separator. Pieces without any separator are cut every `size` characters.
+pub struct Recursive {
+    pub size: usize,
+    pub separators: Vec<String>,
+}
+
+impl Recursive {
+    /// Splits on paragraphs, then lines, sentences and words.
+    pub fn new(size: usize) -> Self {
+        Self { size, separators: ["\n\n", "\n", ". ", " "].into_iter().map(String::from).collect() }
+    }
+
+    fn split(&self, text: &str, offset: usize, separators: &[String], spans: &mut Vec<Range<usize>>) -> Result<()> {
+        let shifted = |span: Range<usize>| offset + span.start..offset + span.end;
+        if char_count(text) <= self.size {
+            spans.push(shifted(0..text.len()));
+            return Ok(());
+        }
+        let Some((separator, rest)) = separators.split_first() else {
+            spans.extend(FixedSize { size: self.size }.spans(text)?.into_iter().map(shifted));
+            return Ok(());
+        };
+
+        // Each piece keeps the separator that ends it.
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for (index, matched) in text.match_indices(separator.as_str()) {
+            pieces.push(start..index + matched.len());
+            start = index + matched.len();
+        }
+        if start < text.len() {
+            pieces.push(start..text.len());
+        }
+
+        let mut current: Option<Range<usize>> = None;
+        for piece in pieces {
+            match current.clone() {
+                Some(merged) if char_count(&text[merged.start..piece.end]) <= self.size => {
+                    current = Some(merged.start..piece.end);
+                }
+                merged => {
+                    spans.extend(merged.map(shifted));
+                    if char_count(&text[piece.clone()]) <= self.size {
+                        current = Some(piece);
+                    } else {
+                        current = None;
+                        self.split(&text[piece.clone()], offset + piece.start, rest, spans)?;
+                    }
+                }
+            }
+        }
+        spans.extend(current.map(shifted));
+        Ok(())
+    }
+}
+
+impl Chunker for Recursive {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let mut spans = Vec::new();
+        self.split(text, 0, &self.separators, &mut spans)?;
+        Ok(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Document;
+
+    fn pieces(size: usize, text: &str) -> Vec<String> {
+        Recursive::new(size).chunk(&Document::new("test.txt", text)).unwrap().into_iter().map(|chunk| chunk.text).collect()
+    }
+
+    #[test]
+    fn splits_on_the_coarsest_separator_that_fits() {
+        let text = "First paragraph.\n\nSecond paragraph, line one.\nLine two.";
+        assert_eq!(pieces(100, text), [text]);
+        assert_eq!(pieces(30, text), ["First paragraph.", "Second paragraph, line one.", "Line two."]);
+        assert_eq!(pieces(12, "One. Two. Three four five."), ["One. Two.", "Three four", "five."]);
+    }
+
+    #[test]
+    fn cuts_words_longer_than_size() {
+        assert_eq!(pieces(4, "ab Donaudampfschiff cd"), ["ab", "Dona", "udam", "pfsc", "hiff", "cd"]);
+    }
+
+    #[test]
+    fn keeps_multibyte_text_on_char_boundaries() {
+        let text = "Ça va?\n\nTrès bien… 日本語です。\nÜnïcödé ✓✓✓ überlängewörter.";
+        for size in 1..30 {
+            let spans = Recursive::new(size).spans(text).unwrap();
+            assert!(spans.iter().all(|span| char_count(&text[span.clone()]) <= size), "size {}", size);
+            assert!(spans.windows(2).all(|pair| pair[0].end <= pair[1].start), "size {}", size);
+        }
+    }
+
+    #[test]
+    fn drops_whitespace_only_chunks() {
+        assert!(pieces(5, "   \n\n\n\n   ").is_empty());
+        assert_eq!(pieces(5, "one\n\n     \n\ntwo"), ["one", "two"]);
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::ops::Range;
+
+use super::{char_count, Chunker, SlidingWindow};
+
+/// Consecutive sentences packed into chunks of up to `size` characters. Sentences end at `.`,
+/// `!` or `?` followed by whitespace, and at blank lines; a sentence longer than `size` is
+/// split between words, and a word longer than `size` every `size` characters.
+pub struct Sentences {
+    pub size: usize,
+}
+
+impl Chunker for Sentences {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let fits = |span: Range<usize>| char_count(text[span].trim()) <= self.size;
+        let mut spans = Vec::new();
+        let mut current: Option<Range<usize>> = None;
+        for sentence in sentences(text) {
+            if !fits(sentence.clone()) {
+                spans.extend(current.take());
+                let words = SlidingWindow { size: self.size, overlap: 0 };
+                spans.extend(words.spans(&text[sentence.clone()])?.into_iter()
+                    .map(|span| sentence.start + span.start..sentence.start + span.end));
+                continue;
+            }
+            match current.clone() {
+                Some(packed) if fits(packed.start..sentence.end) => current = Some(packed.start..sentence.end),
+                packed => {
+                    spans.extend(packed);
+                    current = Some(sentence);
+                }
+            }
+        }
+        spans.extend(current);
+        Ok(spans)
+    }
+}
+
+/// Byte ranges of the sentences of `text`, each including the whitespace after it.
+fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let ends_sentence = match c {
+            '.' | '!' | '?' => {
+                // Repeated punctuation and closing quotes or brackets belong to the sentence.
+                while chars.next_if(|&(_, next)| matches!(next, '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '”' | '’')).is_some() {}
+                chars.peek().is_none_or(|&(_, next)| next.is_whitespace())
+            }
+            '\n' => chars.peek().is_some_and(|&(_, next)| next == '\n'),
+            _ => false,
+        };
+        if ends_sentence {
+            while chars.next_if(|&(_, next)| next.is_whitespace()).is_some() {}
+            let end = chars.peek().map_or(text.len(), |&(offset, _)| offset);
+            sentences.push(start..end);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(start..text.len());
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Document;
+
+    fn pieces(size: usize, text: &str) -> Vec<String> {
+        Sentences { size }.chunk(&Document::new("test.txt", text)).unwrap().into_iter().map(|chunk| chunk.text).collect()
+    }
+
+    #[test]
+    fn packs_whole_sentences() {
+        let text = "First one. Second one! Third? \"Quoted.\" Last";
+        assert_eq!(pieces(22, text), ["First one. Second one!", "Third? \"Quoted.\" Last"]);
+        assert_eq!(pieces(100, "Ends here.\n\nNew paragraph without a stop\n\nv1.2 stays whole."), [
+            "Ends here.\n\nNew paragraph without a stop\n\nv1.2 stays whole."
+        ]);
+        assert_eq!(pieces(10, "Dr.Who said hi. Bye.\n\nNext"), ["Dr.Who", "said hi.", "Bye.\n\nNext"]);
+    }
+
+    #[test]
+    fn splits_overlong_sentences_and_words() {
+        assert_eq!(pieces(12, "Short one. This sentence is far too long. End."), [
+            "Short one.", "This", "sentence is", "far too", "long.", "End."
+        ]);
+        assert_eq!(pieces(4, "Ünïcödé!"), ["Ünïc", "ödé!"]);
+    }
+
+    #[test]
+    fn keeps_multibyte_text_on_char_boundaries() {
+        let text = "Ça va? Très bien… 日本語です。 Ünïcödé ✓✓✓!";
+        for size in 1..20 {
+            for span in (Sentences { size }).spans(text).unwrap() {
+                assert!(char_count(text[span].trim()) <= size, "size {}", size);
+            }
+        }
+    }
+
+    #[test]
+    fn drops_whitespace_only_chunks() {
+        assert!(pieces(10, "  \n\n \n\n\t").is_empty());
+        assert_eq!(pieces(10, "One.   \n\n\n\n   Two."), ["One.", "Two."]);
+    }
+}
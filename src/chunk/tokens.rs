@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use std::{fs, ops::Range, path::Path};
+use tokenizers::Tokenizer;
+
+use super::{Chunk, Chunker, Document, DEFAULT_CHUNK_TOKENS};
+
+/// Windows of whole words measured in tokens of the embedding model's own tokenizer, so that
+/// no chunk is longer than the model reads. `size` counts the special tokens the model adds,
+/// such as `[CLS]` and `[SEP]`; each window after the first starts with the last words of the
+/// previous one, as many as fit in `overlap` tokens. Words longer than a whole window are
+/// split between word pieces.
+pub struct TokenChunker {
+    tokenizer: Tokenizer,
+    size: usize,
+    overlap: usize,
+    /// Tokens the model adds to every input.
+    special_tokens: usize,
+    /// Longest input the model reads; it truncates longer chunks.
+    max_length: usize,
+}
+
+impl TokenChunker {
+    /// Loads a `tokenizer.json`, such as the one next to a model's weights on the Hugging Face hub.
+    ///
+    /// The model's input limit is the `max_seq_length` of a `sentence_bert_config.json` next to
+    /// it, else the tokenizer's truncation length, else the `max_position_embeddings` of a
+    /// `config.json` next to it, else [`DEFAULT_CHUNK_TOKENS`].
+    pub fn from_file(path: impl AsRef<Path>, size: usize, overlap: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let tokenizer = Tokenizer::from_file(path)
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Failed to load tokenizer from {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let sentence_transformers_limit = config_value(&dir.join("sentence_bert_config.json"), "max_seq_length")?;
+        let truncation_limit = tokenizer.get_truncation().map(|truncation| truncation.max_length);
+
+        let chunker = Self::new(tokenizer, size, overlap)?;
+        Ok(match sentence_transformers_limit.or(truncation_limit) {
+            Some(max_length) => chunker.with_max_length(max_length),
+            None => match config_value(&dir.join("config.json"), "max_position_embeddings")? {
+                Some(max_length) => chunker.with_max_length(max_length),
+                None => chunker,
+            },
+        })
+    }
+
+    /// Takes the model's input limit from the tokenizer's truncation length, if it has one.
+    pub fn new(mut tokenizer: Tokenizer, size: usize, overlap: usize) -> Result<Self> {
+        let max_length = tokenizer.get_truncation().map_or(DEFAULT_CHUNK_TOKENS, |truncation| truncation.max_length);
+        // Chunks are measured in full, so the tokenizer must not cut them short.
+        tokenizer.with_truncation(None).map_err(anyhow::Error::msg)?;
+        tokenizer.with_padding(None);
+        let special_tokens = tokenizer.encode("", true).map_err(anyhow::Error::msg)?.len();
+        anyhow::ensure!(
+            size > special_tokens,
+            "Chunks of {} tokens leave no room for text next to {} special tokens", size, special_tokens
+        );
+        Ok(Self { tokenizer, size, overlap, special_tokens, max_length })
+    }
+
+    /// Sets the longest input the model reads.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Number of tokens the model sees for `text`, special tokens included.
+    pub fn count(&self, text: &str) -> Result<usize> {
+        Ok(self.tokenizer.encode(text, true).map_err(anyhow::Error::msg)?.len())
+    }
+}
+
+impl Chunker for TokenChunker {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let budget = self.size - self.special_tokens;
+        let encoding = self.tokenizer.encode(text, false).map_err(anyhow::Error::msg)?;
+
+        // Words as (byte range, tokens), with words longer than the budget cut into pieces.
+        let mut words: Vec<(Range<usize>, usize)> = Vec::new();
+        let mut previous_word = None;
+        for (&(start, end), &word) in encoding.get_offsets().iter().zip(encoding.get_word_ids()) {
+            match words.last_mut() {
+                Some((span, tokens)) if word.is_some() && word == previous_word && *tokens < budget => {
+                    span.end = end;
+                    *tokens += 1;
+                }
+                _ => words.push((start..end, 1)),
+            }
+            previous_word = word;
+        }
+
+        let (spans, tokens): (Vec<Range<usize>>, Vec<usize>) = words.into_iter().unzip();
+        let weight = |range: Range<usize>| tokens[range].iter().sum::<usize>();
+        Ok(super::window::pack(&spans, weight, budget, self.overlap))
+    }
+
+    /// Records the token count of every chunk. Chunks can exceed the model's limit when `size`
+    /// does, or rarely when a trimmed chunk tokenizes differently from the same words in context.
+    fn chunk(&self, document: &Document) -> Result<Vec<Chunk>> {
+        let body = document.body();
+        let mut chunks = Vec::new();
+        for span in self.spans(body)? {
+            let Some(span) = super::trim_span(body, span) else { continue };
+            let mut chunk = document.chunk(chunks.len(), span);
+            chunk.tokens = Some(self.count(&chunk.text)?);
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        Some(self.max_length)
+    }
+}
+
+/// The number under `key` in the JSON file at `path`, or `None` if there is no such file or key.
+fn config_value(path: &Path, key: &str) -> Result<Option<usize>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let config: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(config.get(key).and_then(serde_json::Value::as_u64).map(|value| value as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// A WordPiece tokenizer in the shape of BERT's, with a vocabulary of a few words and a
+    /// truncation length of 16.
+    fn tokenizer_json(truncation: bool) -> String {
+        let vocab: serde_json::Map<String, serde_json::Value> = [
+            "[PAD]", "[UNK]", "[CLS]", "[SEP]", "the", "cat", "sat", "on", "mat", ".", "un", "##believ", "##able",
+        ]
+            .into_iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), id.into()))
+            .collect();
+        serde_json::json!({
+            "version": "1.0",
+            "truncation": truncation.then(|| serde_json::json!({
+                "direction": "Right", "max_length": 16, "strategy": "LongestFirst", "stride": 0,
+            })),
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": { "type": "BertPreTokenizer" },
+            "post_processor": { "type": "BertProcessing", "sep": ["[SEP]", 3], "cls": ["[CLS]", 2] },
+            "decoder": null,
+            "model": {
+                "type": "WordPiece",
+                "unk_token": "[UNK]",
+                "continuing_subword_prefix": "##",
+                "max_input_chars_per_word": 100,
+                "vocab": vocab,
+            },
+        }).to_string()
+    }
+
+    fn chunker(size: usize, overlap: usize) -> TokenChunker {
+        TokenChunker::new(Tokenizer::from_str(&tokenizer_json(true)).unwrap(), size, overlap).unwrap()
+    }
+
+    fn pieces(chunker: &TokenChunker, text: &str) -> Vec<String> {
+        chunker.spans(text).unwrap().into_iter().map(|span| text[span].to_string()).collect()
+    }
+
+    #[test]
+    fn counts_special_tokens_and_reads_the_truncation_length() {
+        let chunker = chunker(8, 0);
+        assert_eq!(chunker.special_tokens, 2);
+        assert_eq!(chunker.max_tokens(), Some(16));
+        assert_eq!(chunker.count("the cat sat.").unwrap(), 6);
+        assert_eq!(chunker.with_max_length(4).max_tokens(), Some(4));
+        assert!(TokenChunker::new(Tokenizer::from_str(&tokenizer_json(true)).unwrap(), 2, 0).is_err());
+    }
+
+    #[test]
+    fn overlaps_whole_words() {
+        // Three tokens of text next to [CLS] and [SEP], stepping back one word.
+        assert_eq!(pieces(&chunker(5, 1), "the cat sat on the mat"), ["the cat sat", "sat on the", "the mat"]);
+        assert_eq!(pieces(&chunker(5, 0), "the cat sat on the mat"), ["the cat sat", "on the mat"]);
+    }
+
+    #[test]
+    fn splits_words_longer_than_a_window_between_word_pieces() {
+        assert_eq!(pieces(&chunker(4, 0), "the unbelievable cat"), ["the", "unbeliev", "able cat"]);
+    }
+
+    #[test]
+    fn records_token_counts_and_drops_empty_chunks() {
+        let chunks = chunker(6, 0).chunk(&Document::new("test.txt", "  the cat sat on the mat.  \n\n")).unwrap();
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(texts, ["the cat sat on", "the mat."]);
+        assert_eq!(chunks.iter().map(|chunk| chunk.tokens).collect::<Vec<_>>(), [Some(6), Some(5)]);
+        assert!(chunker(6, 0).chunk(&Document::new("test.txt", " \n ")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_the_input_limit_from_the_model_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.json");
+
+        fs::write(&path, tokenizer_json(false)).unwrap();
+        assert_eq!(TokenChunker::from_file(&path, 8, 0).unwrap().max_tokens(), Some(DEFAULT_CHUNK_TOKENS));
+        fs::write(dir.path().join("config.json"), r#"{"max_position_embeddings": 512}"#).unwrap();
+        assert_eq!(TokenChunker::from_file(&path, 8, 0).unwrap().max_tokens(), Some(512));
+
+        fs::write(&path, tokenizer_json(true)).unwrap();
+        assert_eq!(TokenChunker::from_file(&path, 8, 0).unwrap().max_tokens(), Some(16));
+        fs::write(dir.path().join("sentence_bert_config.json"), r#"{"max_seq_length": 256}"#).unwrap();
+        assert_eq!(TokenChunker::from_file(&path, 8, 0).unwrap().max_tokens(), Some(256));
+    }
+}
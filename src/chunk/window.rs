@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::ops::Range;
+
+use super::{char_count, Chunker, FixedSize};
+
+/// Windows of whole words up to `size` characters. Each window after the first starts with
+/// the last words of the previous one, as many as fit in `overlap` characters. Words longer
+/// than `size` are cut into pieces of `size` characters.
+pub struct SlidingWindow {
+    pub size: usize,
+    pub overlap: usize,
+}
+
+impl Chunker for SlidingWindow {
+    fn spans(&self, text: &str) -> Result<Vec<Range<usize>>> {
+        let mut words = Vec::new();
+        for word in self::words(text) {
+            if char_count(&text[word.clone()]) <= self.size {
+                words.push(word);
+            } else {
+                let pieces = FixedSize { size: self.size }.spans(&text[word.clone()])?;
+                words.extend(pieces.into_iter().map(|piece| word.start + piece.start..word.start + piece.end));
+            }
+        }
+
+        let width = |range: Range<usize>| char_count(&text[words[range.start].start..words[range.end - 1].end]);
+        Ok(pack(&words, width, self.size, self.overlap))
+    }
+}
+
+/// Groups consecutive `words` into spans whose `weight` is at most `size`, where `weight`
+/// measures the words at a range of indices. Each span after the first starts with the last
+/// words of the previous one, as many as weigh at most `overlap`.
+pub(super) fn pack(words: &[Range<usize>], weight: impl Fn(Range<usize>) -> usize, size: usize, overlap: usize) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut first = 0;
+    while first < words.len() {
+        let mut last = first;
+        while last + 1 < words.len() && weight(first..last + 2) <= size {
+            last += 1;
+        }
+        spans.push(words[first].start..words[last].end);
+        if last + 1 == words.len() {
+            break;
+        }
+
+        // Step back over as many words as the overlap allows, but always move forward.
+        let mut next = last + 1;
+        while next > first + 1 && weight(next - 1..last + 1) <= overlap {
+            next -= 1;
+        }
+        first = next;
+    }
+    spans
+}
+
+/// Byte ranges of the runs of non-whitespace in `text`.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (offset, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                words.push(start..offset);
+            }
+        } else if start.is_none() {
+            start = Some(offset);
+        }
+    }
+    if let Some(start) = start {
+        words.push(start..text.len());
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Document;
+
+    fn pieces(chunker: &SlidingWindow, text: &str) -> Vec<String> {
+        chunker.spans(text).unwrap().into_iter().map(|span| text[span].to_string()).collect()
+    }
+
+    #[test]
+    fn overlaps_whole_words() {
+        let window = SlidingWindow { size: 11, overlap: 5 };
+        assert_eq!(pieces(&window, "one two three four five"), ["one two", "two three", "three four", "four five"]);
+        assert_eq!(pieces(&SlidingWindow { size: 11, overlap: 0 }, "one two three four"), ["one two", "three four"]);
+    }
+
+    #[test]
+    fn always_makes_progress_while_overlap_is_below_size() {
+        let text = "ü ßß ééé ✓✓✓✓ 日本語の 文 a bb ccc";
+        for size in 1..12 {
+            for overlap in 0..size {
+                let spans = SlidingWindow { size, overlap }.spans(text).unwrap();
+                assert!(spans.windows(2).all(|pair| pair[0].start < pair[1].start), "size {} overlap {}", size, overlap);
+                assert!(spans.iter().all(|span| char_count(&text[span.clone()]) <= size), "size {} overlap {}", size, overlap);
+                assert_eq!(spans.last().unwrap().end, text.len());
+            }
+        }
+    }
+
+    #[test]
+    fn cuts_words_longer_than_a_window() {
+        let window = SlidingWindow { size: 4, overlap: 1 };
+        assert_eq!(pieces(&window, "ab überlänge cd"), ["ab", "über", "läng", "e cd"]);
+    }
+
+    #[test]
+    fn skips_whitespace() {
+        let window = SlidingWindow { size: 10, overlap: 2 };
+        assert!(window.spans(" \n\t ").unwrap().is_empty());
+        let chunks = window.chunk(&Document::new("test.txt", "\n\n  alpha  \n\n")).unwrap();
+        assert_eq!(chunks.iter().map(|chunk| chunk.text.as_str()).collect::<Vec<_>>(), ["alpha"]);
+    }
+}
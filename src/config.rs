@@ -0,0 +1,393 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::embedder::{cache::DEFAULT_CACHE_PATH, EmbedderConfig, DEFAULT_DIMENSION, DEFAULT_MODEL};
+use crate::qdrant::{QdrantClient, Transport};
+use crate::retry::RetryPolicy;
+use crate::store::{Backend, EmbeddedStore, IndexKind, VectorStore};
+
+pub const CONFIG_FILE_NAME: &str = "rust-vdb.toml";
+pub const DEFAULT_QDRANT_URL: &str = "http://localhost:6333";
+pub const DEFAULT_QDRANT_GRPC_URL: &str = "http://localhost:6334";
+pub const DEFAULT_COLLECTION: &str = "registration_collection";
+pub const DEFAULT_STORE_PATH: &str = ".rust-vdb/store";
+pub const DEFAULT_SQLITE_PATH: &str = ".rust-vdb/store.sqlite";
+
+/// Fully resolved settings.
+pub struct Config {
+    pub qdrant_url: String,
+    pub transport: Transport,
+    pub collection: String,
+    pub embedding: EmbedderConfig,
+    /// Embedding cache file, kept even when caching is disabled so it can still be inspected.
+    pub cache_path: PathBuf,
+    pub store: StoreConfig,
+}
+
+pub struct StoreConfig {
+    pub backend: Backend,
+    /// Directory of the embedded store, or the database file of the sqlite store.
+    pub path: PathBuf,
+    pub index: IndexKind,
+    /// Connection string of the postgres store.
+    pub url: Option<String>,
+}
+
+impl Config {
+    /// A Qdrant client for the configured server.
+    pub fn qdrant_client(&self, http: &Client) -> Result<QdrantClient> {
+        Ok(QdrantClient::builder()
+            .url(&self.qdrant_url)
+            .transport(self.transport)
+            .http_client(http.clone())
+            .build()?)
+    }
+
+    /// Opens the configured vector store.
+    pub async fn open_store(&self, http: &Client) -> Result<Arc<dyn VectorStore>> {
+        match self.store.backend {
+            Backend::Qdrant => Ok(Arc::new(self.qdrant_client(http)?)),
+            Backend::Embedded => Ok(Arc::new(EmbeddedStore::open(&self.store.path, self.store.index)?)),
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => {
+                let url = self.store.url.as_deref()
+                    .context("The postgres store needs a connection string in store.url, VECTOR_STORE_URL or DATABASE_URL")?;
+                Ok(Arc::new(crate::store::PostgresStore::connect(url).await?))
+            }
+            #[cfg(not(feature = "postgres"))]
+            Backend::Postgres => anyhow::bail!("The postgres store requires building with --features postgres"),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => Ok(Arc::new(crate::store::SqliteStore::open(&self.store.path)?)),
+            #[cfg(not(feature = "sqlite"))]
+            Backend::Sqlite => anyhow::bail!("The sqlite store requires building with --features sqlite"),
+        }
+    }
+}
+
+/// One layer of settings; unset values fall through to the layer below.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Layer {
+    pub store: StoreLayer,
+    pub qdrant: QdrantLayer,
+    pub embedding: EmbeddingLayer,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StoreLayer {
+    pub backend: Option<Backend>,
+    pub path: Option<PathBuf>,
+    pub index: Option<IndexKind>,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QdrantLayer {
+    pub url: Option<String>,
+    pub transport: Option<Transport>,
+    pub collection: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingLayer {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub dimension: Option<usize>,
+    pub base_url: Option<String>,
+    /// Name of the environment variable holding the API key.
+    pub api_key_env: Option<String>,
+    /// Only ever set from the command line; keys do not belong in config files.
+    #[serde(skip)]
+    pub api_key: Option<String>,
+    pub model_dir: Option<PathBuf>,
+    pub max_retries: Option<u32>,
+    pub cache: Option<bool>,
+    pub cache_path: Option<PathBuf>,
+}
+
+/// Contents of a `rust-vdb.toml`: top-level settings plus named profiles that override them.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    /// Profile used when none is given on the command line or in `RUST_VDB_PROFILE`.
+    profile: Option<String>,
+    #[serde(flatten)]
+    base: Layer,
+    profiles: HashMap<String, Layer>,
+}
+
+impl Layer {
+    /// Overrides the values of `self` with those set in `other`.
+    fn merge(&mut self, other: Layer) {
+        macro_rules! overlay {
+            ($($section:ident . $field:ident),* $(,)?) => {
+                $(if other.$section.$field.is_some() {
+                    self.$section.$field = other.$section.$field;
+                })*
+            };
+        }
+        overlay!(
+            store.backend, store.path, store.index, store.url,
+            qdrant.url, qdrant.transport, qdrant.collection,
+            embedding.provider, embedding.model, embedding.dimension, embedding.base_url,
+            embedding.api_key_env, embedding.api_key, embedding.model_dir, embedding.max_retries,
+            embedding.cache, embedding.cache_path,
+        );
+    }
+
+    /// Fills in defaults for everything left unset.
+    fn resolve(self) -> Config {
+        let embedding = self.embedding;
+        let provider = embedding.provider.unwrap_or_else(|| "huggingface".to_string());
+        let api_key_env = embedding.api_key_env.or_else(|| match provider.as_str() {
+            "huggingface" | "hf" => Some("HUGGINGFACE_API_KEY".to_string()),
+            "openai" => Some("OPENAI_API_KEY".to_string()),
+            _ => None,
+        });
+        let api_key = embedding.api_key.or_else(|| api_key_env.and_then(|name| env::var(name).ok()));
+        let cache_path = embedding.cache_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_PATH));
+
+        let transport = self.qdrant.transport.unwrap_or_default();
+        let backend = self.store.backend.unwrap_or_default();
+
+        Config {
+            qdrant_url: self.qdrant.url
+                .unwrap_or_else(|| transport.default_url().to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            transport,
+            collection: self.qdrant.collection.unwrap_or_else(|| DEFAULT_COLLECTION.to_string()),
+            embedding: EmbedderConfig {
+                provider,
+                model: embedding.model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+                dimension: embedding.dimension.unwrap_or(DEFAULT_DIMENSION),
+                base_url: embedding.base_url,
+                api_key,
+                model_dir: embedding.model_dir,
+                max_retries: embedding.max_retries.unwrap_or(RetryPolicy::default().max_retries),
+                on_retry: None,
+                cache_path: embedding.cache.unwrap_or(true).then(|| cache_path.clone()),
+            },
+            cache_path,
+            store: StoreConfig {
+                backend,
+                path: self.store.path.unwrap_or_else(|| match backend {
+                    Backend::Sqlite => PathBuf::from(DEFAULT_SQLITE_PATH),
+                    _ => PathBuf::from(DEFAULT_STORE_PATH),
+                }),
+                index: self.store.index.unwrap_or_default(),
+                url: self.store.url,
+            },
+        }
+    }
+
+    /// Settings taken from environment variables.
+    pub fn from_env() -> Result<Self> {
+        let cache = match env::var("EMBEDDING_CACHE") {
+            Ok(value) => Some(!matches!(value.as_str(), "off" | "false" | "0")),
+            Err(_) => None,
+        };
+        Ok(Self {
+            store: StoreLayer {
+                backend: parse_env("VECTOR_STORE")?,
+                path: env::var_os("VECTOR_STORE_PATH").map(PathBuf::from),
+                index: parse_env("VECTOR_STORE_INDEX")?,
+                url: env::var("VECTOR_STORE_URL").or_else(|_| env::var("DATABASE_URL")).ok(),
+            },
+            qdrant: QdrantLayer {
+                url: env::var("QDRANT_URL").ok(),
+                transport: parse_env("QDRANT_TRANSPORT")?,
+                collection: env::var("QDRANT_COLLECTION").ok(),
+            },
+            embedding: EmbeddingLayer {
+                provider: env::var("EMBEDDING_PROVIDER").ok(),
+                model: env::var("EMBEDDING_MODEL").ok(),
+                dimension: parse_env("EMBEDDING_DIMENSION")?,
+                base_url: env::var("EMBEDDING_BASE_URL").or_else(|_| env::var("OPENAI_BASE_URL")).ok(),
+                api_key_env: env::var("EMBEDDING_API_KEY_ENV").ok(),
+                api_key: None,
+                model_dir: env::var_os("EMBEDDING_MODEL_DIR").map(PathBuf::from),
+                max_retries: parse_env("EMBED_MAX_RETRIES")?,
+                cache,
+                cache_path: env::var_os("EMBEDDING_CACHE_PATH").map(PathBuf::from),
+            },
+        })
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str) -> Result<Option<T>>
+where
+    T::Err: Into<anyhow::Error>,
+{
+    match env::var(name) {
+        Ok(value) => {
+            let parsed = value.parse::<T>().map_err(Into::into).with_context(|| format!("Invalid value for {}", name))?;
+            Ok(Some(parsed))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<Option<ConfigFile>> {
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            let file = toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+            Ok(Some(file))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/rust-vdb/rust-vdb.toml`, or `~/.config/rust-vdb/rust-vdb.toml`.
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("rust-vdb").join(CONFIG_FILE_NAME))
+}
+
+/// Resolves settings from, in increasing precedence: built-in defaults, the user config file,
+/// the project config file (`./rust-vdb.toml`, or `config_path` if given), the selected profile
+/// of each file, `env` (usually [`Layer::from_env`]) and finally `cli`.
+///
+/// The profile comes from `profile`, which the command line fills from `--profile` or
+/// `RUST_VDB_PROFILE`, else the `profile` key of the project or user file.
+pub fn load(config_path: Option<&Path>, profile: Option<&str>, env: Layer, cli: Layer) -> Result<Config> {
+    let mut files = Vec::new();
+    if let Some(path) = user_config_path() {
+        files.extend(read_config_file(&path)?);
+    }
+    match config_path {
+        Some(path) => files.push(
+            read_config_file(path)?.with_context(|| format!("Config file {} does not exist", path.display()))?
+        ),
+        None => files.extend(read_config_file(Path::new(CONFIG_FILE_NAME))?),
+    }
+
+    Ok(merge_layers(files, profile, env, cli)?.resolve())
+}
+
+/// Stacks `files` (lowest precedence first), the selected profile of each, `env` and `cli`.
+fn merge_layers(files: Vec<ConfigFile>, profile: Option<&str>, env: Layer, cli: Layer) -> Result<Layer> {
+    let profile = profile.map(String::from)
+        .or_else(|| files.iter().rev().find_map(|file| file.profile.clone()));
+
+    let mut layer = Layer::default();
+    let mut profile_layers = Vec::new();
+    for mut file in files {
+        layer.merge(file.base);
+        if let Some(name) = &profile {
+            profile_layers.extend(file.profiles.remove(name));
+        }
+    }
+    if let Some(name) = &profile {
+        anyhow::ensure!(!profile_layers.is_empty(), "Profile {} is not defined in any config file", name);
+    }
+    for profile_layer in profile_layers {
+        layer.merge(profile_layer);
+    }
+    layer.merge(env);
+    layer.merge(cli);
+    Ok(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(text: &str) -> ConfigFile {
+        toml::from_str(text).unwrap()
+    }
+
+    fn collection(collection: &str) -> Layer {
+        Layer { qdrant: QdrantLayer { collection: Some(collection.to_string()), ..QdrantLayer::default() }, ..Layer::default() }
+    }
+
+    /// The user and project files of the tests; every source sets `qdrant.collection` to its own name.
+    fn files() -> Vec<ConfigFile> {
+        vec![
+            file(r#"
+                profile = "user-default"
+                [qdrant]
+                collection = "user"
+                url = "http://user:6333"
+                [embedding]
+                model = "user-model"
+                [profiles.dev.qdrant]
+                collection = "user-dev"
+                [profiles.user-default.qdrant]
+                collection = "user-default"
+            "#),
+            file(r#"
+                [qdrant]
+                collection = "project"
+                [embedding]
+                dimension = 768
+                [profiles.dev.qdrant]
+                collection = "project-dev"
+                [profiles.dev.store]
+                backend = "embedded"
+            "#),
+        ]
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let merged = |profile, env, cli| merge_layers(files(), profile, env, cli).unwrap().resolve();
+
+        // The user file's `profile` key selects a profile, which beats the project file's top level.
+        let config = merged(None, Layer::default(), Layer::default());
+        assert_eq!(config.collection, "user-default");
+        assert_eq!(config.qdrant_url, "http://user:6333");
+        assert_eq!(config.embedding.model, "user-model");
+        assert_eq!(config.embedding.dimension, 768);
+
+        // Each file's profile overrides both files' top-level settings, the project's over the user's.
+        let config = merged(Some("dev"), Layer::default(), Layer::default());
+        assert_eq!(config.collection, "project-dev");
+        assert_eq!(config.store.backend, Backend::Embedded);
+        assert_eq!(config.embedding.dimension, 768);
+
+        assert_eq!(merged(Some("dev"), collection("env"), Layer::default()).collection, "env");
+        assert_eq!(merged(Some("dev"), collection("env"), collection("cli")).collection, "cli");
+        assert_eq!(merged(None, Layer::default(), collection("cli")).collection, "cli");
+    }
+
+    #[test]
+    fn uses_the_profile_key_of_the_last_file_that_sets_one() {
+        let mut files = files();
+        files[1].profile = Some("dev".to_string());
+        assert_eq!(merge_layers(files, None, Layer::default(), Layer::default()).unwrap().resolve().collection, "project-dev");
+    }
+
+    #[test]
+    fn falls_back_to_defaults_without_files() {
+        let config = merge_layers(Vec::new(), None, Layer::default(), Layer::default()).unwrap().resolve();
+        assert_eq!(config.collection, DEFAULT_COLLECTION);
+        assert_eq!(config.qdrant_url, DEFAULT_QDRANT_URL);
+        assert_eq!(config.embedding.model, DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn rejects_an_undefined_profile() {
+        let error = merge_layers(files(), Some("staging"), Layer::default(), Layer::default()).err().unwrap();
+        assert_eq!(error.to_string(), "Profile staging is not defined in any config file");
+
+        let mut files = files();
+        files[0].profile = Some("missing".to_string());
+        let error = merge_layers(files, None, Layer::default(), Layer::default()).err().unwrap();
+        assert_eq!(error.to_string(), "Profile missing is not defined in any config file");
+    }
+}
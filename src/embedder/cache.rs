@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::{check_embeddings, Embedder};
+
+pub const DEFAULT_CACHE_PATH: &str = ".rust-vdb/embeddings.jsonl";
+
+/// One line of the cache file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    model: String,
+    vector: Vec<f32>,
+}
+
+/// Summary of what the cache file holds.
+pub struct CacheStats {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub entries: usize,
+    /// Entry count per model id.
+    pub models: BTreeMap<String, usize>,
+}
+
+/// Content-addressed store of embeddings, kept as an append-only JSON lines file.
+///
+/// Entries are keyed by a SHA-256 of the model id and the text, so the same paragraph is
+/// never embedded twice by the same model.
+pub struct EmbeddingCache {
+    entries: Mutex<HashMap<String, Vec<f32>>>,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache directory {}", parent.display()))?;
+        }
+
+        let mut entries = HashMap::new();
+        for entry in read_entries(&path)? {
+            entries.insert(entry.key, entry.vector);
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)
+            .with_context(|| format!("Failed to open embedding cache {}", path.display()))?;
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<f32>> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: String, model: &str, vector: Vec<f32>) -> Result<()> {
+        let entry = Entry { key, model: model.to_string(), vector };
+        {
+            let mut writer = self.writer.lock().unwrap();
+            serde_json::to_writer(&mut *writer, &entry)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        self.entries.lock().unwrap().insert(entry.key, entry.vector);
+        Ok(())
+    }
+}
+
+/// Cache key for `text` embedded by `model`.
+///
+/// The text is hashed exactly as it is sent to the provider: even whitespace can change
+/// how a model tokenizes it.
+pub fn cache_key(model: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn read_entries(path: &Path) -> Result<Vec<Entry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        // A line cut short by an interrupted run is skipped rather than failing the whole cache.
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// The entries of the cache file, one per key; a key written more than once keeps its last entry.
+fn read_unique_entries(path: &Path) -> Result<Vec<Entry>> {
+    let mut unique = HashMap::new();
+    for entry in read_entries(path)? {
+        unique.insert(entry.key.clone(), entry);
+    }
+    Ok(unique.into_values().collect())
+}
+
+/// Reports the size of the cache file at `path`.
+pub fn cache_stats(path: impl AsRef<Path>) -> Result<CacheStats> {
+    let path = path.as_ref();
+    let entries = read_unique_entries(path)?;
+    let file_size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+
+    let mut models = BTreeMap::new();
+    for entry in &entries {
+        *models.entry(entry.model.clone()).or_insert(0) += 1;
+    }
+
+    Ok(CacheStats { path: path.to_path_buf(), file_size, entries: entries.len(), models })
+}
+
+/// Removes cached embeddings, either all of them or only those of `model`.
+/// Returns the number of entries removed, counted like [`cache_stats`] counts them.
+pub fn purge_cache(path: impl AsRef<Path>, model: Option<&str>) -> Result<usize> {
+    let path = path.as_ref();
+    let entries = read_unique_entries(path)?;
+
+    let Some(model) = model else {
+        if path.exists() {
+            fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        return Ok(entries.len());
+    };
+
+    let (removed, kept): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| entry.model == model);
+    let mut writer = BufWriter::new(File::create(path)?);
+    for entry in &kept {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(removed.len())
+}
+
+/// Wraps an embedder so that texts already in the cache are not sent to the provider again.
+pub struct CachedEmbedder {
+    inner: Box<dyn Embedder>,
+    cache: EmbeddingCache,
+}
+
+impl CachedEmbedder {
+    pub fn new(inner: Box<dyn Embedder>, cache: EmbeddingCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[async_trait]
+impl Embedder for CachedEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self.inner.model_id();
+        let keys: Vec<String> = texts.iter().map(|text| cache_key(model, text)).collect();
+        let mut vectors: Vec<Option<Vec<f32>>> = keys.iter().map(|key| self.cache.get(key)).collect();
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| vectors[i].is_none()).collect();
+        if !missing.is_empty() {
+            let misses: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let embeddings = self.inner.embed(&misses).await?;
+            check_embeddings(self.inner.as_ref(), misses.len(), &embeddings)?;
+
+            for (i, vector) in missing.into_iter().zip(embeddings) {
+                self.cache.insert(keys[i].clone(), model, vector.clone())?;
+                vectors[i] = Some(vector);
+            }
+        }
+
+        Ok(vectors.into_iter().flatten().collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Embeds a text as its length, counting the texts it was asked for.
+    struct CountingEmbedder {
+        embedded: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Embedder for CountingEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn model_id(&self) -> &str {
+            "counting"
+        }
+    }
+
+    fn write_lines(path: &Path, entries: &[(&str, &str)]) {
+        let lines: Vec<String> = entries.iter()
+            .map(|(key, model)| serde_json::to_string(&Entry { key: key.to_string(), model: model.to_string(), vector: vec![0.0] }).unwrap())
+            .collect();
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    #[tokio::test]
+    async fn texts_differing_in_whitespace_are_embedded_separately() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let cached = |embedded: &Arc<AtomicUsize>| CachedEmbedder::new(
+            Box::new(CountingEmbedder { embedded: Arc::clone(embedded) }),
+            EmbeddingCache::open(&path).unwrap(),
+        );
+
+        let texts = vec!["a  b".to_string(), "a b".to_string()];
+        let embedded = Arc::new(AtomicUsize::new(0));
+        let vectors = cached(&embedded).embed(&texts).await.unwrap();
+        assert_eq!(vectors, vec![vec![4.0], vec![3.0]]);
+        assert_eq!(embedded.load(Ordering::SeqCst), 2);
+
+        // A fresh cache over the same file embeds nothing new.
+        let embedded = Arc::new(AtomicUsize::new(0));
+        assert_eq!(cached(&embedded).embed(&texts).await.unwrap(), vectors);
+        assert_eq!(embedded.load(Ordering::SeqCst), 0);
+        assert_eq!(cache_stats(&path).unwrap().entries, 2);
+    }
+
+    #[test]
+    fn stats_and_purge_count_duplicate_lines_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let lines = [("k1", "a"), ("k1", "a"), ("k2", "a"), ("k3", "b"), ("k3", "b")];
+
+        write_lines(&path, &lines);
+        let stats = cache_stats(&path).unwrap();
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.models, BTreeMap::from([("a".to_string(), 2), ("b".to_string(), 1)]));
+        assert_eq!(purge_cache(&path, None).unwrap(), stats.entries);
+        assert!(!path.exists());
+
+        write_lines(&path, &lines);
+        assert_eq!(purge_cache(&path, Some("a")).unwrap(), 2);
+        assert_eq!(cache_stats(&path).unwrap().entries, 1);
+        assert_eq!(purge_cache(&path, Some("b")).unwrap(), 1);
+    }
+}
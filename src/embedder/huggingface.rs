@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::Embedder;
+use crate::retry::{is_transient, retry_after, seconds, transport_error, Attempt, RetryPolicy};
+
+const INFERENCE_URL: &str = "https://api-inference.huggingface.co/models";
+
+/// The feature-extraction pipeline answers a list of inputs with a matrix of one row per
+/// input, but collapses a single input to a bare vector.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FeatureExtraction {
+    Matrix(Vec<Vec<f32>>),
+    Vector(Vec<f32>),
+}
+
+impl FeatureExtraction {
+    fn into_rows(self) -> Vec<Vec<f32>> {
+        match self {
+            FeatureExtraction::Matrix(rows) => rows,
+            FeatureExtraction::Vector(row) => vec![row],
+        }
+    }
+}
+
+/// Error body of the inference API, e.g. `{"error": "Model ... is currently loading", "estimated_time": 20.0}`.
+#[derive(Deserialize)]
+struct ApiError {
+    error: String,
+    estimated_time: Option<f64>,
+}
+
+/// Embeds text through the Hugging Face inference API.
+pub struct HuggingFaceEmbedder {
+    client: Client,
+    api_key: String,
+    model: String,
+    dimension: usize,
+    retry_policy: RetryPolicy,
+}
+
+impl HuggingFaceEmbedder {
+    pub fn new(client: Client, api_key: String, model: String, dimension: usize) -> Self {
+        Self { client, api_key, model, dimension, retry_policy: RetryPolicy::default() }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    async fn attempt(&self, texts: &[String]) -> Result<Attempt<Vec<Vec<f32>>>> {
+        let response = match self.client.post(format!("{}/{}", INFERENCE_URL, self.model))
+            .bearer_auth(&self.api_key)
+            .json(texts)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => return transport_error(error),
+        };
+
+        let status = response.status();
+        let after = retry_after(&response);
+        let body = response.text().await?;
+
+        if status.is_success() {
+            let embeddings: FeatureExtraction = serde_json::from_str(&body)
+                .context("Failed to deserialize embedding response")?;
+            return Ok(Attempt::Done(embeddings.into_rows()));
+        }
+
+        let (message, estimated_time) = match serde_json::from_str::<ApiError>(&body) {
+            Ok(error) => (error.error, error.estimated_time),
+            Err(_) => (body, None),
+        };
+        if !is_transient(status) {
+            anyhow::bail!("Hugging Face API returned {}: {}", status, message);
+        }
+
+        // While a cold model loads the API answers 503 with an estimate of how long that takes.
+        let after = after.or_else(|| {
+            estimated_time
+                .filter(|seconds| *seconds > 0.0)
+                .and_then(seconds)
+        });
+        Ok(Attempt::Retry { status: Some(status), message, after })
+    }
+}
+
+#[async_trait]
+impl Embedder for HuggingFaceEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.retry_policy.run(|| self.attempt(texts)).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use std::{fs, path::Path, sync::Arc};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+use super::Embedder;
+
+/// sentence-transformers models are trained with inputs of at most 256 word pieces.
+const MAX_SEQUENCE_LENGTH: usize = 256;
+
+/// Runs a sentence-transformers BERT model on the CPU.
+///
+/// The model directory must contain `config.json`, `tokenizer.json` and
+/// `model.safetensors`, as published on the Hugging Face hub.
+pub struct LocalEmbedder {
+    inner: Arc<Model>,
+    model_id: String,
+    dimension: usize,
+}
+
+struct Model {
+    bert: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl LocalEmbedder {
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let config_json = fs::read_to_string(dir.join("config.json"))
+            .with_context(|| format!("Failed to read config.json from {}", dir.display()))?;
+        let config: Config = serde_json::from_str(&config_json).context("Failed to parse config.json")?;
+        let dimension = serde_json::from_str::<serde_json::Value>(&config_json)?["hidden_size"]
+            .as_u64()
+            .context("config.json is missing hidden_size")? as usize;
+
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(anyhow::Error::msg)
+            .context("Failed to load tokenizer.json")?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer.with_truncation(Some(TruncationParams {
+            max_length: MAX_SEQUENCE_LENGTH,
+            ..Default::default()
+        })).map_err(anyhow::Error::msg)?;
+
+        let device = Device::Cpu;
+        // Safety: the weights file is not expected to change while it is mapped.
+        let weights = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DTYPE, &device)?
+        };
+        let bert = BertModel::load(weights, &config).context("Failed to load model weights")?;
+
+        let model_id = dir.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| dir.display().to_string());
+
+        Ok(Self {
+            inner: Arc::new(Model { bert, tokenizer, device }),
+            model_id,
+            dimension,
+        })
+    }
+}
+
+impl Model {
+    /// Encodes a batch, mean-pools the token embeddings over the attention mask and L2-normalizes them.
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let encodings = self.tokenizer.encode_batch(texts, true).map_err(anyhow::Error::msg)?;
+
+        let mut input_ids = Vec::with_capacity(encodings.len());
+        let mut type_ids = Vec::with_capacity(encodings.len());
+        let mut masks = Vec::with_capacity(encodings.len());
+        for encoding in &encodings {
+            input_ids.push(Tensor::new(encoding.get_ids(), &self.device)?);
+            type_ids.push(Tensor::new(encoding.get_type_ids(), &self.device)?);
+            masks.push(Tensor::new(encoding.get_attention_mask(), &self.device)?);
+        }
+        let input_ids = Tensor::stack(&input_ids, 0)?;
+        let type_ids = Tensor::stack(&type_ids, 0)?;
+        let attention_mask = Tensor::stack(&masks, 0)?;
+
+        let hidden = self.bert.forward(&input_ids, &type_ids, Some(&attention_mask))?;
+
+        let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+        let norms = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+        let normalized = pooled.broadcast_div(&norms)?;
+
+        Ok(normalized.to_vec2::<f32>()?)
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let model = Arc::clone(&self.inner);
+        let texts = texts.to_vec();
+        tokio::task::spawn_blocking(move || model.embed(texts)).await?
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use std::path::PathBuf;
+
+use crate::retry::{OnRetry, RetryPolicy};
+
+pub mod cache;
+mod huggingface;
+#[cfg(feature = "local")]
+mod local;
+mod openai;
+
+pub use cache::{CachedEmbedder, EmbeddingCache};
+pub use huggingface::HuggingFaceEmbedder;
+#[cfg(feature = "local")]
+pub use local::LocalEmbedder;
+pub use openai::OpenAiEmbedder;
+
+pub const DEFAULT_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+pub const DEFAULT_DIMENSION: usize = 384;
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// A provider that turns text into dense vectors.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embeds every input, returning one vector per text in the same order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Length of the vectors produced by this embedder.
+    fn dimension(&self) -> usize;
+
+    /// Identifier of the underlying model, e.g. `sentence-transformers/all-MiniLM-L6-v2`.
+    fn model_id(&self) -> &str;
+}
+
+/// Embeds a single text, such as a search query.
+pub async fn embed_text(embedder: &dyn Embedder, text: String) -> Result<Vec<f32>> {
+    embedder.embed(&[text]).await?
+        .pop()
+        .context("Embedding provider returned no vectors")
+}
+
+/// Checks that a provider response holds `expected` vectors of the embedder's dimension.
+pub fn check_embeddings(embedder: &dyn Embedder, expected: usize, embeddings: &[Vec<f32>]) -> Result<()> {
+    anyhow::ensure!(
+        embeddings.len() == expected,
+        "Embedding provider returned {} vectors for {} inputs", embeddings.len(), expected
+    );
+    if let Some(vector) = embeddings.iter().find(|vector| vector.len() != embedder.dimension()) {
+        anyhow::bail!(
+            "Embedding provider returned a {}-dimensional vector, expected {}",
+            vector.len(), embedder.dimension()
+        );
+    }
+    Ok(())
+}
+
+/// Which embedding provider to use and how to reach it.
+pub struct EmbedderConfig {
+    /// `huggingface`, `openai` or `local`.
+    pub provider: String,
+    pub model: String,
+    pub dimension: usize,
+    /// Base URL of an OpenAI-compatible server.
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Directory holding the model files for the `local` provider.
+    pub model_dir: Option<PathBuf>,
+    pub max_retries: u32,
+    /// Notified when a request to the provider is retried.
+    pub on_retry: Option<OnRetry>,
+    /// Embedding cache file; `None` disables caching.
+    pub cache_path: Option<PathBuf>,
+}
+
+impl EmbedderConfig {
+    /// Builds the configured provider, wrapped in the embedding cache when one is set.
+    pub fn build(&self, client: &Client) -> Result<Box<dyn Embedder>> {
+        let embedder = self.build_provider(client)?;
+        match &self.cache_path {
+            Some(path) => Ok(Box::new(CachedEmbedder::new(embedder, EmbeddingCache::open(path)?))),
+            None => Ok(embedder),
+        }
+    }
+
+    fn build_provider(&self, client: &Client) -> Result<Box<dyn Embedder>> {
+        let retry_policy = RetryPolicy { max_retries: self.max_retries, on_retry: self.on_retry.clone(), ..RetryPolicy::default() };
+        let model = self.model.clone();
+
+        match self.provider.as_str() {
+            "huggingface" | "hf" => {
+                let api_key = self.api_key.clone().context("Expected a Hugging Face API key")?;
+                Ok(Box::new(HuggingFaceEmbedder::new(client.clone(), api_key, model, self.dimension).with_retry_policy(retry_policy)))
+            }
+            "openai" => {
+                let base_url = self.base_url.clone().unwrap_or_else(|| openai::DEFAULT_BASE_URL.to_string());
+                Ok(Box::new(OpenAiEmbedder::new(client.clone(), base_url, self.api_key.clone(), model, self.dimension).with_retry_policy(retry_policy)))
+            }
+            #[cfg(feature = "local")]
+            "local" => {
+                let dir = self.model_dir.as_ref().context("The local provider needs a model directory")?;
+                let embedder = LocalEmbedder::from_dir(dir)?;
+                anyhow::ensure!(
+                    embedder.dimension() == self.dimension,
+                    "Local model produces {}-dimensional vectors, expected {}", embedder.dimension(), self.dimension
+                );
+                Ok(Box::new(embedder))
+            }
+            #[cfg(not(feature = "local"))]
+            "local" => anyhow::bail!("The local provider requires building with --features local"),
+            other => anyhow::bail!("Unknown embedding provider: {}", other),
+        }
+    }
+}
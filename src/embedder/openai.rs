@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::Embedder;
+use crate::retry::{is_transient, retry_after, transport_error, Attempt, RetryPolicy};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Embeds text through any server implementing the OpenAI `/v1/embeddings` endpoint,
+/// such as Ollama, vLLM or the llama.cpp server.
+pub struct OpenAiEmbedder {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dimension: usize,
+    retry_policy: RetryPolicy,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+impl OpenAiEmbedder {
+    /// `base_url` may be given with or without its `/v1` suffix, e.g. `http://localhost:11434`
+    /// for Ollama; without one, `/v1` is appended.
+    pub fn new(client: Client, base_url: String, api_key: Option<String>, model: String, dimension: usize) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        let base_url = if base_url.ends_with("/v1") { base_url.to_string() } else { format!("{}/v1", base_url) };
+        Self { client, base_url, api_key, model, dimension, retry_policy: RetryPolicy::default() }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    async fn attempt(&self, texts: &[String]) -> Result<Attempt<Vec<Vec<f32>>>> {
+        let mut request = self.client.post(format!("{}/embeddings", self.base_url))
+            .json(&EmbeddingRequest { model: &self.model, input: texts });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(error) => return transport_error(error),
+        };
+        let status = response.status();
+        let after = retry_after(&response);
+        let body = response.text().await?;
+
+        if is_transient(status) {
+            return Ok(Attempt::Retry { status: Some(status), message: body, after });
+        }
+        anyhow::ensure!(status.is_success(), "Embedding request failed with {}: {}", status, body);
+
+        let mut parsed: EmbeddingResponse = serde_json::from_str(&body)
+            .context("Failed to deserialize embedding response")?;
+        // The protocol does not promise that `data` comes back in input order.
+        parsed.data.sort_by_key(|item| item.index);
+
+        Ok(Attempt::Done(parsed.data.into_iter().map(|item| item.embedding).collect()))
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.retry_policy.run(|| self.attempt(texts)).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::test_support::mock_server;
+
+    fn embedder(base_url: String, api_key: Option<&str>) -> OpenAiEmbedder {
+        OpenAiEmbedder::new(Client::new(), base_url, api_key.map(String::from), "test-model".to_string(), 2)
+            .with_retry_policy(RetryPolicy { max_retries: 2, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(10), on_retry: None })
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn sorts_results_by_index() {
+        let body = r#"{"data": [{"embedding": [2.0, 2.0], "index": 1}, {"embedding": [1.0, 1.0], "index": 0}]}"#;
+        let (url, requests) = mock_server(vec![(200, "", body.to_string())]).await;
+
+        let vectors = embedder(url, Some("secret")).embed(&texts(&["first", "second"])).await.unwrap();
+
+        assert_eq!(vectors, vec![vec![1.0, 1.0], vec![2.0, 2.0]]);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].body, serde_json::json!({"model": "test-model", "input": ["first", "second"]}));
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn sends_no_authorization_without_api_key() {
+        let body = r#"{"data": [{"embedding": [1.0, 0.0], "index": 0}]}"#;
+        let (url, requests) = mock_server(vec![(200, "", body.to_string())]).await;
+
+        embedder(url, None).embed(&texts(&["text"])).await.unwrap();
+
+        assert_eq!(requests.lock().unwrap()[0].authorization, None);
+    }
+
+    #[tokio::test]
+    async fn accepts_base_url_with_or_without_v1() {
+        let body = r#"{"data": [{"embedding": [1.0, 0.0], "index": 0}]}"#.to_string();
+        let (url, requests) = mock_server(vec![(200, "", body.clone()), (200, "", body.clone()), (200, "", body)]).await;
+
+        for base_url in [url.clone(), format!("{}/v1", url), format!("{}/v1/", url)] {
+            embedder(base_url, None).embed(&texts(&["text"])).await.unwrap();
+        }
+
+        let paths: Vec<String> = requests.lock().unwrap().iter().map(|request| request.path.clone()).collect();
+        assert_eq!(paths, vec!["/v1/embeddings"; 3]);
+    }
+
+    #[tokio::test]
+    async fn retries_after_too_many_requests() {
+        let body = r#"{"data": [{"embedding": [1.0, 0.0], "index": 0}]}"#;
+        let (url, requests) = mock_server(vec![
+            (429, "retry-after: 0\r\n", r#"{"error": "slow down"}"#.to_string()),
+            (200, "", body.to_string()),
+        ]).await;
+
+        let vectors = embedder(url, None).embed(&texts(&["text"])).await.unwrap();
+
+        assert_eq!(vectors, vec![vec![1.0, 0.0]]);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn fails_on_client_errors_without_retrying() {
+        let (url, requests) = mock_server(vec![(401, "", r#"{"error": "bad key"}"#.to_string())]).await;
+
+        let error = embedder(url, Some("wrong")).embed(&texts(&["text"])).await.unwrap_err();
+
+        assert!(error.to_string().contains("401"), "{}", error);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+
+use crate::chunk::{stable_ids, Chunk};
+use crate::embedder::{check_embeddings, Embedder, DEFAULT_BATCH_SIZE};
+use crate::rate_limit::RateLimiter;
+use crate::store::{Collection, Distance, Point, PointId, VectorStore};
+
+/// Points fetched per page when reading back a collection.
+const SCROLL_PAGE_SIZE: usize = 256;
+
+/// Tuning knobs for [`ingest`].
+pub struct IngestOptions {
+    /// Number of texts sent to the embedding provider per request.
+    pub batch_size: usize,
+    /// Maximum number of embedding requests in flight at once.
+    pub concurrency: usize,
+    /// Upper bound on embedding requests per second, if the provider enforces one.
+    pub requests_per_second: Option<f64>,
+    /// Number of points sent to the vector store per upsert.
+    pub upsert_batch_size: usize,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: 4,
+            requests_per_second: None,
+            upsert_batch_size: 256,
+        }
+    }
+}
+
+/// Creates the collection `name` sized for `embedder` and loads `chunks` into it, one point per
+/// chunk. Returns the number of points written.
+///
+/// An existing collection of the same name is an error unless `recreate` is set, in which case
+/// it is dropped first; [`sync`] updates one in place instead.
+pub async fn load_data(store: &Arc<dyn VectorStore>, name: &str, embedder: &dyn Embedder, chunks: Vec<Chunk>, distance: Distance, recreate: bool, options: &IngestOptions) -> Result<usize> {
+    if store.list_collections().await?.iter().any(|existing| existing == name) {
+        anyhow::ensure!(
+            recreate,
+            "Collection {} already exists; pass --recreate to replace it, or use sync to update it", name
+        );
+        store.drop_collection(name).await?;
+    }
+    let collection = Collection { name: name.to_string(), vector_size: embedder.dimension(), distance };
+    store.create_collection(&collection).await?;
+    ingest(store, name, embedder, chunks, options).await
+}
+
+/// What [`sync`] changed.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Chunks that had no point yet.
+    pub added: usize,
+    /// Chunks re-embedded because their embedding model changed, or because their text changed
+    /// and they replace the point at the same `chunk_index` of the same source.
+    pub updated: usize,
+    /// Points deleted because their text is gone from the source and no chunk replaced them.
+    pub removed: usize,
+    /// Points left as they were.
+    pub unchanged: usize,
+}
+
+/// Stored state of a point, as far as [`sync`] cares.
+struct StoredChunk {
+    content_hash: Option<String>,
+    model: Option<String>,
+    source: Option<String>,
+    chunk_index: Option<u64>,
+}
+
+/// Brings `name` in line with `chunks` without starting over: only chunks that are new or whose
+/// `content_hash` or `model` payload differs are embedded, and points of the same sources whose
+/// text no longer exists are deleted, as are points that record no source at all. The collection
+/// is created if it does not exist.
+///
+/// Point ids derive from the text, so an edited chunk gets a new id and its old point is deleted.
+/// The report counts the pair as one update when the old point had the same `chunk_index`.
+///
+/// Unchanged points keep their payload, so their location fields, metadata and `ingested_at`
+/// still describe the text as it was when it was embedded.
+pub async fn sync(store: &Arc<dyn VectorStore>, name: &str, embedder: &dyn Embedder, chunks: Vec<Chunk>, distance: Distance, options: &IngestOptions) -> Result<SyncReport> {
+    if !store.list_collections().await?.iter().any(|existing| existing == name) {
+        let collection = Collection { name: name.to_string(), vector_size: embedder.dimension(), distance };
+        store.create_collection(&collection).await?;
+    }
+    let mut stored = stored_chunks(store.as_ref(), name).await?;
+
+    let mut report = SyncReport::default();
+    let mut changed = Vec::new();
+    let mut changed_ids = Vec::new();
+    let mut new = Vec::new();
+    for (chunk, id) in chunks.iter().zip(stable_ids(&chunks)) {
+        match stored.remove(&id) {
+            None => new.push(chunk),
+            Some(point) if point.content_hash.as_deref() == Some(chunk.content_hash().as_str())
+                && point.model.as_deref() == Some(embedder.model_id()) => {
+                report.unchanged += 1;
+                continue;
+            }
+            Some(_) => report.updated += 1,
+        }
+        changed.push(chunk.clone());
+        changed_ids.push(id);
+    }
+
+    let sources: HashSet<&str> = chunks.iter().map(|chunk| chunk.source.as_str()).collect();
+    let stale: Vec<(PointId, StoredChunk)> = stored.into_iter()
+        .filter(|(_, point)| point.source.as_deref().is_none_or(|source| sources.contains(source)))
+        .collect();
+
+    let mut replaceable: HashSet<(&str, u64)> = stale.iter()
+        .filter_map(|(_, point)| Some((point.source.as_deref()?, point.chunk_index?)))
+        .collect();
+    let mut replaced = 0;
+    for chunk in new {
+        if replaceable.remove(&(chunk.source.as_str(), chunk.index as u64)) {
+            replaced += 1;
+        } else {
+            report.added += 1;
+        }
+    }
+    report.updated += replaced;
+    report.removed = stale.len() - replaced;
+    let stale: Vec<PointId> = stale.into_iter().map(|(id, _)| id).collect();
+
+    upsert_chunks(store, name, embedder, changed, changed_ids, options).await?;
+    for ids in stale.chunks(options.upsert_batch_size.max(1)) {
+        store.delete(name, ids).await?;
+    }
+    store.flush().await?;
+    Ok(report)
+}
+
+/// Reads the hash, model, source and position of every point in `collection`.
+async fn stored_chunks(store: &dyn VectorStore, collection: &str) -> Result<HashMap<PointId, StoredChunk>> {
+    let field = |payload: &Option<serde_json::Map<String, serde_json::Value>>, key: &str| {
+        payload.as_ref()
+            .and_then(|payload| payload.get(key))
+            .and_then(|value| value.as_str())
+            .map(String::from)
+    };
+
+    let mut stored = HashMap::new();
+    let mut offset = None;
+    loop {
+        let page = store.scroll(collection, SCROLL_PAGE_SIZE, offset).await?;
+        for record in page.points {
+            stored.insert(record.id, StoredChunk {
+                content_hash: field(&record.payload, "content_hash"),
+                model: field(&record.payload, "model"),
+                source: field(&record.payload, "source"),
+                chunk_index: record.payload.as_ref()
+                    .and_then(|payload| payload.get("chunk_index"))
+                    .and_then(|value| value.as_u64()),
+            });
+        }
+        match page.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => return Ok(stored),
+        }
+    }
+}
+
+/// Embeds `chunks` and upserts them into `collection` under their [stable ids](stable_ids),
+/// returning the number of points written.
+pub async fn ingest(store: &Arc<dyn VectorStore>, collection: &str, embedder: &dyn Embedder, chunks: Vec<Chunk>, options: &IngestOptions) -> Result<usize> {
+    let ids = stable_ids(&chunks);
+    let written = upsert_chunks(store, collection, embedder, chunks, ids, options).await?;
+    store.flush().await?;
+    Ok(written)
+}
+
+/// Embeds `chunks` and upserts them under `ids`, one id per chunk. Payloads also record the
+/// embedding model and the time of ingestion in seconds since the Unix epoch.
+///
+/// Embedding requests run concurrently and points are upserted as soon as a full batch is ready,
+/// so at most `concurrency` embedding batches and two upsert batches are held in memory at a time.
+async fn upsert_chunks(store: &Arc<dyn VectorStore>, collection: &str, embedder: &dyn Embedder, chunks: Vec<Chunk>, ids: Vec<PointId>, options: &IngestOptions) -> Result<usize> {
+    let batch_size = options.batch_size.max(1);
+    let limiter = options.requests_per_second
+        .map(|rate| RateLimiter::new(rate, options.concurrency))
+        .transpose()?;
+
+    let (sender, mut receiver) = mpsc::channel::<Point>(1);
+    let upserter = {
+        let store = Arc::clone(store);
+        let collection = collection.to_string();
+        tokio::spawn(async move {
+            let mut written = 0;
+            while let Some(points) = receiver.recv().await {
+                written += points.ids.len();
+                store.upsert(&collection, points).await?;
+            }
+            Ok::<_, anyhow::Error>(written)
+        })
+    };
+
+    let batches = chunks.chunks(batch_size)
+        .enumerate()
+        .map(|(batch_index, batch)| (batch_index * batch_size, batch));
+    let mut embedded = stream::iter(batches)
+        .map(|(start, batch)| {
+            let limiter = limiter.as_ref();
+            async move {
+                if let Some(limiter) = limiter {
+                    limiter.acquire().await;
+                }
+                let texts: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+                let vectors = embedder.embed(&texts).await
+                    .with_context(|| format!("Failed to embed chunks starting at {}", start))?;
+                check_embeddings(embedder, batch.len(), &vectors)?;
+                Ok::<_, anyhow::Error>((start, batch, vectors))
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1));
+
+    let ingested_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut pending = empty_batch(options.upsert_batch_size);
+    while let Some((start, batch, vectors)) = embedded.try_next().await? {
+        for (offset, (chunk, vector)) in batch.iter().zip(vectors).enumerate() {
+            pending.ids.push(ids[start + offset]);
+            pending.vectors.push(vector);
+            let mut payload = chunk.payload();
+            payload.insert("model".to_string(), embedder.model_id().into());
+            payload.insert("ingested_at".to_string(), ingested_at.into());
+            pending.payloads.get_or_insert_with(Vec::new).push(payload);
+        }
+        if pending.ids.len() >= options.upsert_batch_size {
+            let points = std::mem::replace(&mut pending, empty_batch(options.upsert_batch_size));
+            if sender.send(points).await.is_err() {
+                // The upserter stopped early; its error is reported below.
+                break;
+            }
+        }
+    }
+    if !pending.ids.is_empty() {
+        let _ = sender.send(pending).await;
+    }
+    drop(sender);
+
+    upserter.await?
+}
+
+fn empty_batch(capacity: usize) -> Point {
+    Point {
+        ids: Vec::with_capacity(capacity),
+        vectors: Vec::with_capacity(capacity),
+        payloads: Some(Vec::with_capacity(capacity)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{paragraphs, Document};
+    use crate::store::{EmbeddedStore, IndexKind};
+    use async_trait::async_trait;
+
+    struct LengthEmbedder;
+
+    #[async_trait]
+    impl Embedder for LengthEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|text| vec![text.len() as f32, 1.0]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn model_id(&self) -> &str {
+            "length"
+        }
+    }
+
+    #[tokio::test]
+    async fn load_replaces_an_existing_collection_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn VectorStore> = Arc::new(EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap());
+        let chunks = |text: &str| paragraphs(&Document::new("test.txt", text)).unwrap();
+        let options = IngestOptions::default();
+
+        let written = load_data(&store, "docs", &LengthEmbedder, chunks("one\n\ntwo\n\nthree"), Distance::Dot, false, &options).await.unwrap();
+        assert_eq!(written, 3);
+
+        let error = load_data(&store, "docs", &LengthEmbedder, chunks("four"), Distance::Dot, false, &options).await.unwrap_err();
+        assert!(error.to_string().contains("--recreate"), "{}", error);
+        assert_eq!(store.count("docs").await.unwrap(), 3);
+
+        load_data(&store, "docs", &LengthEmbedder, chunks("four"), Distance::Dot, true, &options).await.unwrap();
+        assert_eq!(store.count("docs").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn sync_reports_edits_insertions_and_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn VectorStore> = Arc::new(EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap());
+        let sync = |text: &'static str| {
+            let store = Arc::clone(&store);
+            async move {
+                let chunks = paragraphs(&Document::new("test.txt", text)).unwrap();
+                sync(&store, "docs", &LengthEmbedder, chunks, Distance::Dot, &IngestOptions::default()).await.unwrap()
+            }
+        };
+        let counts = |report: SyncReport| (report.added, report.updated, report.removed, report.unchanged);
+        let texts = || async {
+            let page = store.scroll("docs", 10, None).await.unwrap();
+            let mut texts: Vec<String> = page.points.into_iter()
+                .map(|point| point.payload.unwrap()["text"].as_str().unwrap().to_string())
+                .collect();
+            texts.sort();
+            texts
+        };
+
+        assert_eq!(counts(sync("one\n\ntwo\n\nthree").await), (3, 0, 0, 0));
+        assert_eq!(counts(sync("one\n\ntwo\n\nthree").await), (0, 0, 0, 3));
+
+        assert_eq!(counts(sync("one\n\ntwo edited\n\nthree").await), (0, 1, 0, 2));
+        assert_eq!(texts().await, ["one", "three", "two edited"]);
+
+        assert_eq!(counts(sync("zero\n\none\n\ntwo edited\n\nthree").await), (1, 0, 0, 3));
+
+        assert_eq!(counts(sync("zero\n\ntwo edited\n\nthree").await), (0, 0, 1, 3));
+        assert_eq!(texts().await, ["three", "two edited", "zero"]);
+    }
+}
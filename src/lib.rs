@@ -0,0 +1,47 @@
+//! Load text into a vector database and search it by meaning.
+//!
+//! Texts are turned into vectors by an [`Embedder`](embedder::Embedder) and stored in a
+//! [`VectorStore`](store::VectorStore), such as a Qdrant server reached through a
+//! [`QdrantClient`](qdrant::QdrantClient):
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use rust_vdb::{chunk::{paragraphs, Document}, config, embedder::embed_text, ingest::{load_data, IngestOptions}, qdrant::QdrantClient};
+//! use rust_vdb::store::{Condition, Distance, Filter, Range, SearchRequest, VectorStore};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let config = config::load(None, None, config::Layer::from_env()?, Default::default())?;
+//! let http = reqwest::Client::new();
+//! let embedder = config.embedding.build(&http)?;
+//! let qdrant = QdrantClient::builder()
+//!     .url(&config.qdrant_url)
+//!     .transport(config.transport)
+//!     .http_client(http)
+//!     .build()?;
+//! let store: Arc<dyn VectorStore> = Arc::new(qdrant);
+//!
+//! let chunks = paragraphs(&Document::new("faq.txt", "Registration opens in April.\n\nClasses start in May."))?;
+//! load_data(&store, "registration_collection", embedder.as_ref(), chunks, Distance::Cosine, false, &IngestOptions::default()).await?;
+//!
+//! let query = embed_text(embedder.as_ref(), "When can I register?".to_string()).await?;
+//! let filter = Filter::default()
+//!     .must(Condition::equals("source", "faq.txt"))
+//!     .must_not(Condition::range("chunk_index", Range { lt: Some(1.0), ..Range::default() }));
+//! let request = SearchRequest { score_threshold: Some(0.5), filter: Some(filter), ..SearchRequest::new(query, 20) };
+//! for hit in store.search("registration_collection", request).await? {
+//!     println!("{}", hit.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod chunk;
+pub mod config;
+pub mod embedder;
+pub mod ingest;
+pub mod qdrant;
+pub mod rate_limit;
+pub mod retry;
+pub mod store;
+#[cfg(test)]
+mod test_support;
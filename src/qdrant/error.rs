@@ -0,0 +1,59 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub type QdrantResult<T> = std::result::Result<T, QdrantError>;
+
+/// Failure of a request to Qdrant.
+#[derive(Debug, Error)]
+pub enum QdrantError {
+    /// Qdrant answered with a non-2xx status or an error status in the body.
+    #[error("Qdrant returned {status}: {message}")]
+    Api { status: StatusCode, message: String },
+    /// The request never got a response.
+    #[error("Request to Qdrant failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The response body did not have the expected shape.
+    #[error("Failed to parse Qdrant response: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// The response parsed but lacked something it should contain.
+    #[error("Unexpected Qdrant response: {0}")]
+    Unexpected(String),
+    /// The request cannot be expressed over the configured transport, or that transport was
+    /// not compiled in.
+    #[error("Unsupported by the Qdrant client: {0}")]
+    Unsupported(String),
+    /// A request over the gRPC transport failed.
+    #[cfg(feature = "grpc")]
+    #[error("gRPC request to Qdrant failed: {0}")]
+    Grpc(#[from] qdrant_client::QdrantError),
+}
+
+impl QdrantError {
+    /// HTTP status of an API error.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            QdrantError::Api { status, .. } => Some(*status),
+            QdrantError::Http(error) => error.status(),
+            QdrantError::Decode(_) | QdrantError::Unexpected(_) | QdrantError::Unsupported(_) => None,
+            #[cfg(feature = "grpc")]
+            QdrantError::Grpc(qdrant_client::QdrantError::ResponseError { status }) => match status.code() {
+                tonic::Code::NotFound => Some(StatusCode::NOT_FOUND),
+                tonic::Code::AlreadyExists => Some(StatusCode::CONFLICT),
+                tonic::Code::InvalidArgument => Some(StatusCode::BAD_REQUEST),
+                _ => None,
+            },
+            #[cfg(feature = "grpc")]
+            QdrantError::Grpc(_) => None,
+        }
+    }
+
+    /// Whether the collection or point does not exist.
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// Whether the request conflicts with existing state, e.g. creating a collection that exists.
+    pub fn is_conflict(&self) -> bool {
+        self.status() == Some(StatusCode::CONFLICT)
+    }
+}
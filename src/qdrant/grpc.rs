@@ -0,0 +1,544 @@
+use qdrant_client::qdrant::{
+    self, point_id::PointIdOptions, value::Kind, vectors_config, vectors_output::VectorsOptions, CollectionStatus,
+    CountPointsBuilder, CreateCollectionBuilder, DeletePointsBuilder, Distance as GrpcDistance, GetPointsBuilder,
+    PointId as GrpcPointId, PointStruct, PointsIdsList, ScrollPointsBuilder, SearchPointsBuilder, UpdateStatus,
+    UpsertPointsBuilder, Value, VectorParamsBuilder,
+};
+use qdrant_client::{Payload, Qdrant};
+use std::{collections::HashMap, sync::Arc};
+
+use super::{
+    CollectionConfig, CollectionDescription, CollectionInfo, CollectionParams, QdrantError, QdrantResult,
+    UpdateResult, VectorParams, VectorsConfig,
+};
+use crate::store::{Condition, Distance, FieldCondition, Filter, MatchValue, Point, PointId, Record, ScrollPage, SearchRequest, SearchResultItem};
+
+/// Qdrant's gRPC API, usually on port 6334. Speaks protobuf instead of JSON, which makes
+/// large upserts considerably cheaper.
+#[derive(Clone)]
+pub(crate) struct GrpcTransport {
+    client: Arc<Qdrant>,
+}
+
+impl GrpcTransport {
+    pub(crate) fn connect(url: &str) -> QdrantResult<Self> {
+        let client = Qdrant::from_url(url).build()?;
+        Ok(Self { client: Arc::new(client) })
+    }
+
+    pub(crate) async fn list_collections(&self) -> QdrantResult<Vec<CollectionDescription>> {
+        let response = self.client.list_collections().await?;
+        Ok(response.collections.into_iter()
+            .map(|description| CollectionDescription { name: description.name })
+            .collect())
+    }
+
+    pub(crate) async fn create(&self, name: &str, vector_size: usize, distance: Distance) -> QdrantResult<()> {
+        self.client.create_collection(
+            CreateCollectionBuilder::new(name)
+                .vectors_config(VectorParamsBuilder::new(vector_size as u64, grpc_distance(distance)))
+        ).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete(&self, name: &str) -> QdrantResult<()> {
+        self.client.delete_collection(name).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn info(&self, name: &str) -> QdrantResult<CollectionInfo> {
+        let info = self.client.collection_info(name).await?.result
+            .ok_or_else(|| missing("collection info"))?;
+        let vectors = info.config
+            .and_then(|config| config.params)
+            .and_then(|params| params.vectors_config)
+            .and_then(|vectors| vectors.config)
+            .ok_or_else(|| missing("vectors config"))?;
+
+        Ok(CollectionInfo {
+            status: CollectionStatus::try_from(info.status)
+                .map(|status| status.as_str_name().to_lowercase())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            points_count: info.points_count,
+            indexed_vectors_count: info.indexed_vectors_count,
+            segments_count: info.segments_count,
+            config: CollectionConfig {
+                params: CollectionParams {
+                    vectors: match vectors {
+                        vectors_config::Config::Params(params) => VectorsConfig::Single(vector_params(params)?),
+                        vectors_config::Config::ParamsMap(map) => VectorsConfig::Named(
+                            map.map.into_iter()
+                                .map(|(name, params)| Ok((name, vector_params(params)?)))
+                                .collect::<QdrantResult<_>>()?
+                        ),
+                    },
+                },
+            },
+        })
+    }
+
+    pub(crate) async fn upsert(&self, name: &str, points: &Point) -> QdrantResult<UpdateResult> {
+        let mut structs = Vec::with_capacity(points.ids.len());
+        for (i, (id, vector)) in points.ids.iter().zip(&points.vectors).enumerate() {
+            let payload = match points.payloads.as_ref().and_then(|payloads| payloads.get(i)) {
+                Some(payload) => Payload::try_from(serde_json::Value::Object(payload.clone().into_iter().collect()))?,
+                None => Payload::new(),
+            };
+            structs.push(PointStruct::new(grpc_id(*id), vector.clone(), payload));
+        }
+
+        let response = self.client.upsert_points(UpsertPointsBuilder::new(name, structs).wait(true)).await?;
+        update_result(response.result)
+    }
+
+    pub(crate) async fn delete_points(&self, name: &str, ids: &[PointId]) -> QdrantResult<UpdateResult> {
+        let ids = PointsIdsList { ids: ids.iter().map(|&id| grpc_id(id)).collect() };
+        let response = self.client.delete_points(DeletePointsBuilder::new(name).points(ids).wait(true)).await?;
+        update_result(response.result)
+    }
+
+    pub(crate) async fn search(&self, name: &str, request: SearchRequest) -> QdrantResult<Vec<SearchResultItem>> {
+        let mut search = SearchPointsBuilder::new(name, request.vector, request.limit as u64)
+            .offset(request.offset as u64)
+            .with_payload(true)
+            .with_vectors(request.with_vectors);
+        if let Some(threshold) = request.score_threshold {
+            search = search.score_threshold(threshold);
+        }
+        if let Some(filter) = &request.filter {
+            search = search.filter(grpc_filter(filter)?);
+        }
+        let response = self.client.search_points(search).await?;
+        response.result.into_iter()
+            .map(|point| Ok(SearchResultItem {
+                id: point_id(point.id)?,
+                score: point.score,
+                payload: Some(json_payload(point.payload)),
+                vector: json_vectors(point.vectors),
+            }))
+            .collect()
+    }
+
+    pub(crate) async fn scroll(&self, name: &str, limit: usize, offset: Option<PointId>) -> QdrantResult<ScrollPage> {
+        let mut scroll = ScrollPointsBuilder::new(name).limit(limit as u32).with_payload(true).with_vectors(false);
+        if let Some(offset) = offset {
+            scroll = scroll.offset(grpc_id(offset));
+        }
+        let response = self.client.scroll(scroll).await?;
+        let points = response.result.into_iter()
+            .map(|point| Ok(Record { id: point_id(point.id)?, payload: Some(json_payload(point.payload)), vector: None }))
+            .collect::<QdrantResult<_>>()?;
+        let next_page_offset = match response.next_page_offset {
+            Some(id) => Some(point_id(Some(id))?),
+            None => None,
+        };
+        Ok(ScrollPage { points, next_page_offset })
+    }
+
+    pub(crate) async fn count(&self, name: &str) -> QdrantResult<u64> {
+        let response = self.client.count(CountPointsBuilder::new(name).exact(true)).await?;
+        Ok(response.result.ok_or_else(|| missing("count"))?.count)
+    }
+
+    pub(crate) async fn get(&self, name: &str, id: PointId) -> QdrantResult<Record> {
+        let response = self.client.get_points(
+            GetPointsBuilder::new(name, vec![grpc_id(id)]).with_payload(true).with_vectors(true)
+        ).await?;
+        let point = response.result.into_iter().next()
+            .ok_or_else(|| QdrantError::Api { status: reqwest::StatusCode::NOT_FOUND, message: format!("No point with id {}", id) })?;
+
+        Ok(Record {
+            id: point_id(point.id)?,
+            payload: Some(json_payload(point.payload)),
+            vector: json_vectors(point.vectors),
+        })
+    }
+}
+
+/// A single vector as a JSON array, or named vectors as an object of arrays.
+fn json_vectors(vectors: Option<qdrant::VectorsOutput>) -> Option<serde_json::Value> {
+    vectors
+        .and_then(|vectors| vectors.vectors_options)
+        .map(|options| match options {
+            VectorsOptions::Vector(vector) => serde_json::json!(vector.data),
+            VectorsOptions::Vectors(named) => serde_json::Value::Object(
+                named.vectors.into_iter().map(|(name, vector)| (name, serde_json::json!(vector.data))).collect()
+            ),
+        })
+}
+
+fn update_result(result: Option<qdrant::UpdateResult>) -> QdrantResult<UpdateResult> {
+    let result = result.ok_or_else(|| missing("update result"))?;
+    Ok(UpdateResult {
+        operation_id: result.operation_id,
+        status: UpdateStatus::try_from(result.status)
+            .map(|status| status.as_str_name().to_lowercase())
+            .unwrap_or_else(|_| "unknown".to_string()),
+    })
+}
+
+fn grpc_filter(filter: &Filter) -> QdrantResult<qdrant::Filter> {
+    let conditions = |conditions: &[Condition]| conditions.iter().map(grpc_condition).collect::<QdrantResult<Vec<_>>>();
+    Ok(qdrant::Filter {
+        must: conditions(&filter.must)?,
+        should: conditions(&filter.should)?,
+        must_not: conditions(&filter.must_not)?,
+        ..Default::default()
+    })
+}
+
+fn grpc_condition(condition: &Condition) -> QdrantResult<qdrant::Condition> {
+    match condition {
+        Condition::IsEmpty { is_empty } => Ok(qdrant::Condition::is_empty(is_empty.key.clone())),
+        Condition::Field(FieldCondition { key, matches, range }) => {
+            let mut conditions = Vec::new();
+            if let Some(matches) = matches {
+                conditions.push(qdrant::Condition::matches(key.clone(), grpc_match(matches)?));
+            }
+            if let Some(range) = range {
+                conditions.push(qdrant::Condition::range(key.clone(), qdrant::Range {
+                    lt: range.lt,
+                    gt: range.gt,
+                    gte: range.gte,
+                    lte: range.lte,
+                }));
+            }
+            match conditions.len() {
+                1 => Ok(conditions.remove(0)),
+                _ => Ok(nested_filter(qdrant::Filter::must(conditions))),
+            }
+        }
+        Condition::Filter(filter) => Ok(nested_filter(grpc_filter(filter)?)),
+    }
+}
+
+fn nested_filter(filter: qdrant::Filter) -> qdrant::Condition {
+    qdrant::Condition { condition_one_of: Some(qdrant::condition::ConditionOneOf::Filter(filter)) }
+}
+
+/// gRPC matches are typed: keywords, integers or booleans, with `any` limited to one of the first two.
+fn grpc_match(matches: &MatchValue) -> QdrantResult<qdrant::r#match::MatchValue> {
+    let unsupported = |value: &serde_json::Value| QdrantError::Unsupported(format!("Cannot match on {} over gRPC", value));
+    match matches {
+        MatchValue::Value { value } => match value {
+            serde_json::Value::String(value) => Ok(value.clone().into()),
+            serde_json::Value::Bool(value) => Ok((*value).into()),
+            serde_json::Value::Number(number) if number.is_i64() => Ok(number.as_i64().unwrap_or_default().into()),
+            other => Err(unsupported(other)),
+        },
+        MatchValue::Any { any } => {
+            if let Some(keywords) = any.iter().map(|value| value.as_str().map(str::to_string)).collect::<Option<Vec<_>>>() {
+                Ok(keywords.into())
+            } else if let Some(integers) = any.iter().map(serde_json::Value::as_i64).collect::<Option<Vec<_>>>() {
+                Ok(integers.into())
+            } else {
+                Err(unsupported(&serde_json::Value::Array(any.clone())))
+            }
+        }
+    }
+}
+
+fn missing(what: &str) -> QdrantError {
+    QdrantError::Unexpected(format!("Response has no {}", what))
+}
+
+fn grpc_distance(distance: Distance) -> GrpcDistance {
+    match distance {
+        Distance::Cosine => GrpcDistance::Cosine,
+        Distance::Dot => GrpcDistance::Dot,
+        Distance::Euclid => GrpcDistance::Euclid,
+        Distance::Manhattan => GrpcDistance::Manhattan,
+    }
+}
+
+fn vector_params(params: qdrant::VectorParams) -> QdrantResult<VectorParams> {
+    let distance = match GrpcDistance::try_from(params.distance) {
+        Ok(GrpcDistance::Cosine) => Distance::Cosine,
+        Ok(GrpcDistance::Dot) => Distance::Dot,
+        Ok(GrpcDistance::Euclid) => Distance::Euclid,
+        Ok(GrpcDistance::Manhattan) => Distance::Manhattan,
+        _ => return Err(missing("known distance")),
+    };
+    Ok(VectorParams { size: params.size as usize, distance })
+}
+
+fn grpc_id(id: PointId) -> GrpcPointId {
+    match id {
+        PointId::Num(id) => id.into(),
+        PointId::Uuid(id) => id.to_string().into(),
+    }
+}
+
+fn point_id(id: Option<GrpcPointId>) -> QdrantResult<PointId> {
+    match id.and_then(|id| id.point_id_options) {
+        Some(PointIdOptions::Num(id)) => Ok(PointId::Num(id)),
+        Some(PointIdOptions::Uuid(uuid)) => uuid.parse()
+            .map(PointId::Uuid)
+            .map_err(|_| QdrantError::Unsupported(format!("Point id {} is not a valid UUID", uuid))),
+        None => Err(missing("point id")),
+    }
+}
+
+fn json_payload(payload: HashMap<String, Value>) -> serde_json::Map<String, serde_json::Value> {
+    payload.into_iter().map(|(key, value)| (key, json_value(value))).collect()
+}
+
+fn json_value(value: Value) -> serde_json::Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::BoolValue(value)) => value.into(),
+        Some(Kind::IntegerValue(value)) => value.into(),
+        Some(Kind::DoubleValue(value)) => value.into(),
+        Some(Kind::StringValue(value)) => value.into(),
+        Some(Kind::ListValue(list)) => list.values.into_iter().map(json_value).collect(),
+        Some(Kind::StructValue(object)) => serde_json::Value::Object(json_payload(object.fields)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qdrant_client::qdrant::{
+        collections_server::{Collections, CollectionsServer},
+        points_selector::PointsSelectorOneOf,
+        points_server::{Points, PointsServer},
+        vectors::VectorsOptions as InputVectorsOptions,
+    };
+    use std::sync::Mutex;
+    use tonic::{transport::server::TcpIncoming, Request, Response, Status};
+
+    type StubPoint = (GrpcPointId, Vec<f32>, HashMap<String, Value>);
+
+    /// Just enough of Qdrant's collection and point services for the calls the transport makes,
+    /// keeping the points of every collection in memory.
+    #[derive(Clone, Default)]
+    struct Stub {
+        collections: Arc<Mutex<HashMap<String, Vec<StubPoint>>>>,
+        filters: Arc<Mutex<Vec<qdrant::Filter>>>,
+    }
+
+    /// Implements `$service` with the given methods and answers every other one with `unimplemented`.
+    macro_rules! stub_service {
+        ($service:ident { $($implemented:tt)* } $($name:ident($request:ty) -> $response:ty;)*) => {
+            #[tonic::async_trait]
+            impl $service for Stub {
+                $($implemented)*
+                $(
+                    async fn $name(&self, _request: Request<$request>) -> Result<Response<$response>, Status> {
+                        Err(Status::unimplemented(stringify!($name)))
+                    }
+                )*
+            }
+        };
+    }
+
+    fn operation() -> qdrant::PointsOperationResponse {
+        qdrant::PointsOperationResponse {
+            result: Some(qdrant::UpdateResult { operation_id: Some(0), status: UpdateStatus::Completed as i32 }),
+            ..Default::default()
+        }
+    }
+
+    stub_service!(Collections {
+        async fn create(&self, request: Request<qdrant::CreateCollection>) -> Result<Response<qdrant::CollectionOperationResponse>, Status> {
+            let name = request.into_inner().collection_name;
+            let created = self.collections.lock().unwrap().insert(name, Vec::new()).is_none();
+            Ok(Response::new(qdrant::CollectionOperationResponse { result: created, ..Default::default() }))
+        }
+
+        async fn delete(&self, request: Request<qdrant::DeleteCollection>) -> Result<Response<qdrant::CollectionOperationResponse>, Status> {
+            let deleted = self.collections.lock().unwrap().remove(&request.into_inner().collection_name).is_some();
+            Ok(Response::new(qdrant::CollectionOperationResponse { result: deleted, ..Default::default() }))
+        }
+
+        async fn list(&self, _request: Request<qdrant::ListCollectionsRequest>) -> Result<Response<qdrant::ListCollectionsResponse>, Status> {
+            let collections = self.collections.lock().unwrap().keys()
+                .map(|name| qdrant::CollectionDescription { name: name.clone() })
+                .collect();
+            Ok(Response::new(qdrant::ListCollectionsResponse { collections, ..Default::default() }))
+        }
+    }
+        get(qdrant::GetCollectionInfoRequest) -> qdrant::GetCollectionInfoResponse;
+        update(qdrant::UpdateCollection) -> qdrant::CollectionOperationResponse;
+        update_aliases(qdrant::ChangeAliases) -> qdrant::CollectionOperationResponse;
+        list_collection_aliases(qdrant::ListCollectionAliasesRequest) -> qdrant::ListAliasesResponse;
+        list_aliases(qdrant::ListAliasesRequest) -> qdrant::ListAliasesResponse;
+        collection_cluster_info(qdrant::CollectionClusterInfoRequest) -> qdrant::CollectionClusterInfoResponse;
+        collection_exists(qdrant::CollectionExistsRequest) -> qdrant::CollectionExistsResponse;
+        update_collection_cluster_setup(qdrant::UpdateCollectionClusterSetupRequest) -> qdrant::UpdateCollectionClusterSetupResponse;
+        create_shard_key(qdrant::CreateShardKeyRequest) -> qdrant::CreateShardKeyResponse;
+        delete_shard_key(qdrant::DeleteShardKeyRequest) -> qdrant::DeleteShardKeyResponse;
+    );
+
+    stub_service!(Points {
+        async fn upsert(&self, request: Request<qdrant::UpsertPoints>) -> Result<Response<qdrant::PointsOperationResponse>, Status> {
+            let request = request.into_inner();
+            let mut collections = self.collections.lock().unwrap();
+            let points = collections.get_mut(&request.collection_name).ok_or_else(|| Status::not_found("collection"))?;
+            for point in request.points {
+                let id = point.id.ok_or_else(|| Status::invalid_argument("point without id"))?;
+                let vector = match point.vectors.and_then(|vectors| vectors.vectors_options) {
+                    Some(InputVectorsOptions::Vector(vector)) => vector.data,
+                    _ => return Err(Status::invalid_argument("point without a single vector")),
+                };
+                points.retain(|(existing, _, _)| *existing != id);
+                points.push((id, vector, point.payload));
+            }
+            Ok(Response::new(operation()))
+        }
+
+        async fn delete(&self, request: Request<qdrant::DeletePoints>) -> Result<Response<qdrant::PointsOperationResponse>, Status> {
+            let request = request.into_inner();
+            let ids = match request.points.and_then(|selector| selector.points_selector_one_of) {
+                Some(PointsSelectorOneOf::Points(list)) => list.ids,
+                _ => return Err(Status::invalid_argument("expected a list of ids")),
+            };
+            let mut collections = self.collections.lock().unwrap();
+            let points = collections.get_mut(&request.collection_name).ok_or_else(|| Status::not_found("collection"))?;
+            points.retain(|(id, _, _)| !ids.contains(id));
+            Ok(Response::new(operation()))
+        }
+
+        async fn search(&self, request: Request<qdrant::SearchPoints>) -> Result<Response<qdrant::SearchResponse>, Status> {
+            let request = request.into_inner();
+            self.filters.lock().unwrap().extend(request.filter);
+            let collections = self.collections.lock().unwrap();
+            let points = collections.get(&request.collection_name).ok_or_else(|| Status::not_found("collection"))?;
+            let mut result: Vec<qdrant::ScoredPoint> = points.iter()
+                .map(|(id, vector, payload)| qdrant::ScoredPoint {
+                    id: Some(id.clone()),
+                    payload: payload.clone(),
+                    score: vector.iter().zip(&request.vector).map(|(a, b)| a * b).sum(),
+                    ..Default::default()
+                })
+                .collect();
+            result.sort_by(|a, b| b.score.total_cmp(&a.score));
+            result.truncate(request.limit as usize);
+            Ok(Response::new(qdrant::SearchResponse { result, ..Default::default() }))
+        }
+
+        async fn scroll(&self, request: Request<qdrant::ScrollPoints>) -> Result<Response<qdrant::ScrollResponse>, Status> {
+            let request = request.into_inner();
+            let collections = self.collections.lock().unwrap();
+            let points = collections.get(&request.collection_name).ok_or_else(|| Status::not_found("collection"))?;
+            let start = match &request.offset {
+                Some(offset) => points.iter().position(|(id, _, _)| id == offset).unwrap_or(points.len()),
+                None => 0,
+            };
+            let limit = request.limit.unwrap_or(10) as usize;
+            let result = points[start..].iter()
+                .take(limit)
+                .map(|(id, _, payload)| qdrant::RetrievedPoint { id: Some(id.clone()), payload: payload.clone(), ..Default::default() })
+                .collect();
+            let next_page_offset = points.get(start + limit).map(|(id, _, _)| id.clone());
+            Ok(Response::new(qdrant::ScrollResponse { result, next_page_offset, ..Default::default() }))
+        }
+    }
+        get(qdrant::GetPoints) -> qdrant::GetResponse;
+        update_vectors(qdrant::UpdatePointVectors) -> qdrant::PointsOperationResponse;
+        delete_vectors(qdrant::DeletePointVectors) -> qdrant::PointsOperationResponse;
+        set_payload(qdrant::SetPayloadPoints) -> qdrant::PointsOperationResponse;
+        overwrite_payload(qdrant::SetPayloadPoints) -> qdrant::PointsOperationResponse;
+        delete_payload(qdrant::DeletePayloadPoints) -> qdrant::PointsOperationResponse;
+        clear_payload(qdrant::ClearPayloadPoints) -> qdrant::PointsOperationResponse;
+        create_field_index(qdrant::CreateFieldIndexCollection) -> qdrant::PointsOperationResponse;
+        delete_field_index(qdrant::DeleteFieldIndexCollection) -> qdrant::PointsOperationResponse;
+        search_batch(qdrant::SearchBatchPoints) -> qdrant::SearchBatchResponse;
+        search_groups(qdrant::SearchPointGroups) -> qdrant::SearchGroupsResponse;
+        recommend(qdrant::RecommendPoints) -> qdrant::RecommendResponse;
+        recommend_batch(qdrant::RecommendBatchPoints) -> qdrant::RecommendBatchResponse;
+        recommend_groups(qdrant::RecommendPointGroups) -> qdrant::RecommendGroupsResponse;
+        discover(qdrant::DiscoverPoints) -> qdrant::DiscoverResponse;
+        discover_batch(qdrant::DiscoverBatchPoints) -> qdrant::DiscoverBatchResponse;
+        count(qdrant::CountPoints) -> qdrant::CountResponse;
+        update_batch(qdrant::UpdateBatchPoints) -> qdrant::UpdateBatchResponse;
+        query(qdrant::QueryPoints) -> qdrant::QueryResponse;
+        query_batch(qdrant::QueryBatchPoints) -> qdrant::QueryBatchResponse;
+        query_groups(qdrant::QueryPointGroups) -> qdrant::QueryGroupsResponse;
+        facet(qdrant::FacetCounts) -> qdrant::FacetResponse;
+        search_matrix_pairs(qdrant::SearchMatrixPoints) -> qdrant::SearchMatrixPairsResponse;
+        search_matrix_offsets(qdrant::SearchMatrixPoints) -> qdrant::SearchMatrixOffsetsResponse;
+    );
+
+    /// Serves `stub` on a free local port and connects a transport to it.
+    async fn connect(stub: Stub) -> GrpcTransport {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CollectionsServer::new(stub.clone()))
+                .add_service(PointsServer::new(stub))
+                .serve_with_incoming(incoming),
+        );
+        GrpcTransport::connect(&url).unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_trips_collections_and_points_through_a_stub_server() {
+        let stub = Stub::default();
+        let transport = connect(stub.clone()).await;
+        let uuid = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, b"chunk");
+
+        transport.create("docs", 2, Distance::Dot).await.unwrap();
+        let names: Vec<_> = transport.list_collections().await.unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, ["docs"]);
+
+        let payload = |source: &str| HashMap::from([("source".to_string(), serde_json::json!(source))]);
+        let points = Point {
+            ids: vec![PointId::Num(u64::MAX), PointId::Uuid(uuid)],
+            vectors: vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+            payloads: Some(vec![payload("a.txt"), payload("b.txt")]),
+        };
+        assert_eq!(transport.upsert("docs", &points).await.unwrap().status, "completed");
+
+        let mut request = SearchRequest::new(vec![0.2, 0.9], 2);
+        request.filter = Some(Filter::must_match("source", "b.txt"));
+        let hits = transport.search("docs", request).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), [PointId::Uuid(uuid), PointId::Num(u64::MAX)]);
+        assert_eq!(hits[0].payload.as_ref().unwrap()["source"], "b.txt");
+        assert_eq!(stub.filters.lock().unwrap().len(), 1);
+
+        let page = transport.scroll("docs", 1, None).await.unwrap();
+        assert_eq!(page.points[0].id, PointId::Num(u64::MAX));
+        assert_eq!(page.next_page_offset, Some(PointId::Uuid(uuid)));
+        let page = transport.scroll("docs", 1, page.next_page_offset).await.unwrap();
+        assert_eq!(page.points[0].id, PointId::Uuid(uuid));
+        assert_eq!(page.next_page_offset, None);
+
+        transport.delete_points("docs", &[PointId::Num(u64::MAX)]).await.unwrap();
+        let page = transport.scroll("docs", 10, None).await.unwrap();
+        assert_eq!(page.points.iter().map(|point| point.id).collect::<Vec<_>>(), [PointId::Uuid(uuid)]);
+
+        transport.delete("docs").await.unwrap();
+        assert!(transport.list_collections().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trips_numeric_and_uuid_ids() {
+        let uuid = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, b"chunk");
+        for id in [PointId::Num(0), PointId::Num(u64::MAX), PointId::Uuid(uuid)] {
+            assert_eq!(point_id(Some(grpc_id(id))).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn rejects_ids_that_are_not_uuids() {
+        let error = point_id(Some(GrpcPointId::from("not-a-uuid".to_string()))).unwrap_err();
+        assert!(matches!(error, QdrantError::Unsupported(_)), "{}", error);
+        assert!(matches!(point_id(None), Err(QdrantError::Unexpected(_))));
+    }
+
+    #[test]
+    fn maps_typed_matches_and_rejects_floats() {
+        assert!(grpc_match(&MatchValue::Value { value: serde_json::json!("faq") }).is_ok());
+        assert!(grpc_match(&MatchValue::Value { value: serde_json::json!(2023) }).is_ok());
+        assert!(grpc_match(&MatchValue::Value { value: serde_json::json!(true) }).is_ok());
+        assert!(grpc_match(&MatchValue::Any { any: vec![serde_json::json!(1), serde_json::json!(2)] }).is_ok());
+
+        let error = grpc_match(&MatchValue::Value { value: serde_json::json!(1.5) }).unwrap_err();
+        assert!(matches!(error, QdrantError::Unsupported(_)), "{}", error);
+        let error = grpc_match(&MatchValue::Any { any: vec![serde_json::json!("a"), serde_json::json!(1)] }).unwrap_err();
+        assert!(matches!(error, QdrantError::Unsupported(_)), "{}", error);
+    }
+}
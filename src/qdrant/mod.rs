@@ -0,0 +1,404 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{fmt, str::FromStr};
+
+use crate::config::{DEFAULT_QDRANT_GRPC_URL, DEFAULT_QDRANT_URL};
+use crate::store::{Collection, Distance, Point, PointId, Record, ScrollPage, SearchRequest, SearchResultItem, VectorStore};
+
+mod error;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod types;
+
+pub use error::{QdrantError, QdrantResult};
+pub use types::*;
+
+/// Protocol used to talk to Qdrant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// JSON over HTTP, port 6333 by default.
+    #[default]
+    Rest,
+    /// Protobuf over gRPC, port 6334 by default. Requires the `grpc` feature.
+    Grpc,
+}
+
+impl Transport {
+    pub fn default_url(self) -> &'static str {
+        match self {
+            Transport::Rest => DEFAULT_QDRANT_URL,
+            Transport::Grpc => DEFAULT_QDRANT_GRPC_URL,
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Transport::Rest => "rest",
+            Transport::Grpc => "grpc",
+        })
+    }
+}
+
+impl FromStr for Transport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "rest" | "http" => Ok(Transport::Rest),
+            "grpc" => Ok(Transport::Grpc),
+            _ => anyhow::bail!("Unknown transport {}, expected rest or grpc", s),
+        }
+    }
+}
+
+/// Connection to a Qdrant server. Cheap to clone.
+#[derive(Clone)]
+pub struct QdrantClient {
+    connection: Connection,
+}
+
+#[derive(Clone)]
+enum Connection {
+    Rest { http: Client, url: String },
+    #[cfg(feature = "grpc")]
+    Grpc(grpc::GrpcTransport),
+}
+
+/// Builds a [`QdrantClient`].
+pub struct QdrantClientBuilder {
+    url: Option<String>,
+    transport: Transport,
+    http: Option<Client>,
+}
+
+impl QdrantClientBuilder {
+    /// Server URL; defaults to `http://localhost:6333` for REST and `http://localhost:6334` for gRPC.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Shares an existing HTTP client, e.g. one also used for embedding requests.
+    pub fn http_client(mut self, http: Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    pub fn build(self) -> QdrantResult<QdrantClient> {
+        let url = self.url.as_deref().unwrap_or(self.transport.default_url()).trim_end_matches('/');
+        let connection = match self.transport {
+            Transport::Rest => Connection::Rest { http: self.http.unwrap_or_default(), url: url.to_string() },
+            #[cfg(feature = "grpc")]
+            Transport::Grpc => Connection::Grpc(grpc::GrpcTransport::connect(url)?),
+            #[cfg(not(feature = "grpc"))]
+            Transport::Grpc => {
+                return Err(QdrantError::Unsupported("The gRPC transport requires building with --features grpc".to_string()));
+            }
+        };
+        Ok(QdrantClient { connection })
+    }
+}
+
+impl QdrantClient {
+    pub fn builder() -> QdrantClientBuilder {
+        QdrantClientBuilder { url: None, transport: Transport::Rest, http: None }
+    }
+
+    /// Handle to the collection `name`, which need not exist yet.
+    pub fn collection(&self, name: impl Into<String>) -> CollectionHandle {
+        CollectionHandle { client: self.clone(), name: name.into() }
+    }
+
+    pub async fn list_collections(&self) -> QdrantResult<Vec<CollectionDescription>> {
+        match &self.connection {
+            Connection::Rest { http, url } => {
+                let list: CollectionsList = send(http.get(format!("{}/collections", url))).await?;
+                Ok(list.collections)
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.list_collections().await,
+        }
+    }
+}
+
+/// Sends `request` and unwraps the `result` of Qdrant's response envelope, turning non-2xx
+/// statuses and error bodies into [`QdrantError::Api`].
+async fn send<T: DeserializeOwned>(request: RequestBuilder) -> QdrantResult<T> {
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        let message = serde_json::from_str::<Response<serde_json::Value>>(&body).ok()
+            .and_then(|envelope| envelope.status.error())
+            .unwrap_or(body);
+        return Err(QdrantError::Api { status, message });
+    }
+
+    let envelope: Response<T> = serde_json::from_str(&body)?;
+    if let Some(message) = envelope.status.error() {
+        return Err(QdrantError::Api { status, message });
+    }
+    match envelope.result {
+        Some(result) => Ok(result),
+        None => Err(QdrantError::Unexpected("Response has no result".to_string())),
+    }
+}
+
+/// Operations on a single collection.
+#[derive(Clone)]
+pub struct CollectionHandle {
+    client: QdrantClient,
+    name: String,
+}
+
+impl CollectionHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn create(&self, vector_size: usize, distance: Distance) -> QdrantResult<()> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                let body = CreateCollection { vectors: VectorParams { size: vector_size, distance } };
+                let _: bool = send(http.put(rest_url(url, &self.name, "")).json(&body)).await?;
+                Ok(())
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.create(&self.name, vector_size, distance).await,
+        }
+    }
+
+    /// Inserts or replaces a batch of points.
+    ///
+    /// Waits until the points are written so that failures are reported here.
+    pub async fn upsert(&self, points: &Point) -> QdrantResult<UpdateResult> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                send(http.put(rest_url(url, &self.name, "/points?wait=true")).json(&UpsertBatch { batch: points })).await
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.upsert(&self.name, points).await,
+        }
+    }
+
+    pub async fn delete_points(&self, ids: &[PointId]) -> QdrantResult<UpdateResult> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                send(http.post(rest_url(url, &self.name, "/points/delete?wait=true")).json(&DeletePoints { points: ids })).await
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.delete_points(&self.name, ids).await,
+        }
+    }
+
+    /// Returns the points closest to `request.vector`, best match first.
+    pub async fn search(&self, request: SearchRequest) -> QdrantResult<Vec<SearchResultItem>> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                let query = SearchQuery {
+                    vector: request.vector,
+                    limit: request.limit,
+                    offset: request.offset,
+                    score_threshold: request.score_threshold,
+                    filter: request.filter,
+                    with_payload: true,
+                    with_vector: request.with_vectors,
+                };
+                send(http.post(rest_url(url, &self.name, "/points/search")).json(&query)).await
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.search(&self.name, request).await,
+        }
+    }
+
+    /// A page of points with their payloads, starting at id `offset`.
+    pub async fn scroll(&self, limit: usize, offset: Option<PointId>) -> QdrantResult<ScrollPage> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                let query = ScrollQuery { limit, offset, with_payload: true, with_vector: false };
+                send(http.post(rest_url(url, &self.name, "/points/scroll")).json(&query)).await
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.scroll(&self.name, limit, offset).await,
+        }
+    }
+
+    pub async fn info(&self) -> QdrantResult<CollectionInfo> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => send(http.get(rest_url(url, &self.name, ""))).await,
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.info(&self.name).await,
+        }
+    }
+
+    pub async fn delete(&self) -> QdrantResult<()> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                let _: bool = send(http.delete(rest_url(url, &self.name, ""))).await?;
+                Ok(())
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.delete(&self.name).await,
+        }
+    }
+
+    /// Exact number of points in the collection.
+    pub async fn count(&self) -> QdrantResult<u64> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => {
+                let result: CountResult = send(http.post(rest_url(url, &self.name, "/points/count")).json(&CountQuery { exact: true })).await?;
+                Ok(result.count)
+            }
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.count(&self.name).await,
+        }
+    }
+
+    pub async fn get(&self, id: PointId) -> QdrantResult<Record> {
+        match &self.client.connection {
+            Connection::Rest { http, url } => send(http.get(rest_url(url, &self.name, &format!("/points/{}", id)))).await,
+            #[cfg(feature = "grpc")]
+            Connection::Grpc(grpc) => grpc.get(&self.name, id).await,
+        }
+    }
+}
+
+fn rest_url(base: &str, collection: &str, path: &str) -> String {
+    format!("{}/collections/{}{}", base, collection, path)
+}
+
+#[async_trait]
+impl VectorStore for QdrantClient {
+    async fn create_collection(&self, collection: &Collection) -> anyhow::Result<()> {
+        Ok(self.collection(&collection.name).create(collection.vector_size, collection.distance).await?)
+    }
+
+    async fn drop_collection(&self, name: &str) -> anyhow::Result<()> {
+        Ok(self.collection(name).delete().await?)
+    }
+
+    async fn upsert(&self, collection: &str, points: Point) -> anyhow::Result<()> {
+        self.collection(collection).upsert(&points).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[PointId]) -> anyhow::Result<()> {
+        self.collection(collection).delete_points(ids).await?;
+        Ok(())
+    }
+
+    async fn search(&self, collection: &str, request: SearchRequest) -> anyhow::Result<Vec<SearchResultItem>> {
+        Ok(self.collection(collection).search(request).await?)
+    }
+
+    async fn count(&self, collection: &str) -> anyhow::Result<u64> {
+        Ok(self.collection(collection).count().await?)
+    }
+
+    async fn scroll(&self, collection: &str, limit: usize, offset: Option<PointId>) -> anyhow::Result<ScrollPage> {
+        Ok(self.collection(collection).scroll(limit, offset).await?)
+    }
+
+    async fn list_collections(&self) -> anyhow::Result<Vec<String>> {
+        let collections = QdrantClient::list_collections(self).await?;
+        Ok(collections.into_iter().map(|description| description.name).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use std::sync::{Arc, Mutex};
+
+    use crate::test_support::{mock_server, Recorded};
+
+    async fn client(responses: Vec<(u16, &'static str, String)>) -> (QdrantClient, Arc<Mutex<Vec<Recorded>>>) {
+        let (url, requests) = mock_server(responses).await;
+        (QdrantClient::builder().url(url).build().unwrap(), requests)
+    }
+
+    fn api_error(error: QdrantError) -> (StatusCode, String) {
+        match error {
+            QdrantError::Api { status, message } => (status, message),
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unwraps_the_result() {
+        let (client, requests) = client(vec![(200, "", r#"{"result": {"count": 3}, "status": "ok", "time": 0.001}"#.to_string())]).await;
+
+        assert_eq!(client.collection("docs").count().await.unwrap(), 3);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/collections/docs/points/count");
+        assert_eq!(requests[0].body, serde_json::json!({"exact": true}));
+    }
+
+    #[tokio::test]
+    async fn maps_not_found_to_an_api_error() {
+        let body = r#"{"status": {"error": "Not found: Collection `missing` doesn't exist!"}, "time": 0.0}"#;
+        let (client, _) = client(vec![(404, "", body.to_string())]).await;
+
+        let error = client.collection("missing").info().await.unwrap_err();
+
+        assert!(error.is_not_found());
+        assert!(!error.is_conflict());
+        assert_eq!(api_error(error), (StatusCode::NOT_FOUND, "Not found: Collection `missing` doesn't exist!".to_string()));
+    }
+
+    #[tokio::test]
+    async fn maps_conflict_to_an_api_error() {
+        let body = r#"{"status": {"error": "Wrong input: Collection `docs` already exists!"}, "time": 0.0}"#;
+        let (client, _) = client(vec![(409, "", body.to_string())]).await;
+
+        let error = client.collection("docs").create(4, Distance::Cosine).await.unwrap_err();
+
+        assert!(error.is_conflict());
+        assert!(!error.is_not_found());
+        assert_eq!(api_error(error), (StatusCode::CONFLICT, "Wrong input: Collection `docs` already exists!".to_string()));
+    }
+
+    #[tokio::test]
+    async fn keeps_a_body_that_is_not_an_envelope() {
+        let (client, _) = client(vec![(502, "", "upstream unavailable".to_string())]).await;
+
+        let error = client.collection("docs").info().await.unwrap_err();
+
+        assert_eq!(error.status(), Some(StatusCode::BAD_GATEWAY));
+        assert_eq!(api_error(error), (StatusCode::BAD_GATEWAY, "upstream unavailable".to_string()));
+    }
+
+    #[tokio::test]
+    async fn maps_an_error_status_in_a_successful_response() {
+        let body = r#"{"result": null, "status": {"error": "Service internal error: disk full"}, "time": 0.0}"#;
+        let (client, _) = client(vec![(200, "", body.to_string())]).await;
+
+        let error = client.collection("docs").count().await.unwrap_err();
+
+        assert!(!error.is_not_found() && !error.is_conflict());
+        assert_eq!(api_error(error), (StatusCode::OK, "Service internal error: disk full".to_string()));
+    }
+
+    #[tokio::test]
+    async fn reports_a_missing_result_as_unexpected() {
+        let (client, _) = client(vec![(200, "", r#"{"status": "ok", "time": 0.0}"#.to_string())]).await;
+
+        let error = client.collection("docs").count().await.unwrap_err();
+
+        assert!(matches!(error, QdrantError::Unexpected(_)), "{:?}", error);
+        assert_eq!(error.status(), None);
+    }
+}
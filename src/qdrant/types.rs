@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::store::{Distance, Filter, Point, PointId};
+
+#[derive(Serialize)]
+pub struct CreateCollection {
+    pub vectors: VectorParams,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VectorParams {
+    pub size: usize,
+    pub distance: Distance,
+}
+
+/// Upsert in column form: `{"batch": {"ids": [...], "vectors": [...], "payloads": [...]}}`.
+#[derive(Serialize)]
+pub struct UpsertBatch<'a> {
+    pub batch: &'a Point,
+}
+
+#[derive(Serialize)]
+pub struct DeletePoints<'a> {
+    pub points: &'a [PointId],
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub vector: Vec<f32>,
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    #[serde(default)]
+    pub with_payload: bool,
+    #[serde(default)]
+    pub with_vector: bool,
+}
+
+#[derive(Serialize)]
+pub struct ScrollQuery {
+    pub limit: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<PointId>,
+    pub with_payload: bool,
+    pub with_vector: bool,
+}
+
+#[derive(Serialize)]
+pub struct CountQuery {
+    pub exact: bool,
+}
+
+/// Envelope around every Qdrant response: `{"result": ..., "status": "ok", "time": 0.001}`.
+#[derive(Deserialize)]
+pub struct Response<T> {
+    pub result: Option<T>,
+    pub status: ResponseStatus,
+    #[serde(default)]
+    pub time: f64,
+}
+
+/// `"ok"`, or `{"error": "..."}` when the request failed.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ResponseStatus {
+    Status(String),
+    Error { error: String },
+}
+
+impl ResponseStatus {
+    /// The error message, if this status reports a failure.
+    pub fn error(self) -> Option<String> {
+        match self {
+            ResponseStatus::Status(status) if status == "ok" => None,
+            ResponseStatus::Status(status) => Some(status),
+            ResponseStatus::Error { error } => Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionsList {
+    pub collections: Vec<CollectionDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionDescription {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionInfo {
+    /// `green`, `yellow` or `red`.
+    pub status: String,
+    pub points_count: Option<u64>,
+    pub indexed_vectors_count: Option<u64>,
+    pub segments_count: u64,
+    pub config: CollectionConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionConfig {
+    pub params: CollectionParams,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionParams {
+    pub vectors: VectorsConfig,
+}
+
+/// A single unnamed vector per point, or several named ones.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum VectorsConfig {
+    Single(VectorParams),
+    Named(HashMap<String, VectorParams>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateResult {
+    pub operation_id: Option<u64>,
+    /// `acknowledged` or `completed`.
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CountResult {
+    pub count: u64,
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::{sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// Token bucket that allows `rate` acquisitions per second with bursts of up to `burst`.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: usize) -> Result<Self> {
+        anyhow::ensure!(rate.is_finite() && rate > 0.0, "Requests per second must be a positive number, got {}", rate);
+        let burst = burst.max(1) as f64;
+        Ok(Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket { tokens: burst, refilled_at: Instant::now() }),
+        })
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_rates_that_are_not_positive() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(RateLimiter::new(rate, 1).is_err(), "accepted {}", rate);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn allows_a_burst_then_paces_at_the_rate() {
+        let limiter = RateLimiter::new(10.0, 2).unwrap();
+        let start = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.acquire().await;
+        let third = start.elapsed();
+        assert!(third >= Duration::from_millis(100) && third < Duration::from_millis(101), "{:?}", third);
+
+        limiter.acquire().await;
+        let fourth = start.elapsed();
+        assert!(fourth >= Duration::from_millis(200) && fourth < Duration::from_millis(201), "{:?}", fourth);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_no_more_than_the_burst() {
+        let limiter = RateLimiter::new(10.0, 1).unwrap();
+        limiter.acquire().await;
+        tokio::time::sleep(Duration::from_secs(10)).await;
+
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100), "{:?}", start.elapsed());
+    }
+}
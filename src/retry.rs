@@ -0,0 +1,195 @@
+use anyhow::Result;
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use std::{fmt, future::Future, sync::Arc, time::Duration};
+use thiserror::Error;
+
+/// Returned once every attempt allowed by a [`RetryPolicy`] has failed.
+#[derive(Debug, Error)]
+#[error("Giving up after {attempts} attempts: {message}")]
+pub struct RetriesExhausted {
+    pub attempts: u32,
+    /// Status of the last response, if the last attempt got one.
+    pub status: Option<StatusCode>,
+    pub message: String,
+}
+
+/// Outcome of a single attempt of a retried operation.
+pub enum Attempt<T> {
+    Done(T),
+    /// The attempt failed transiently; `after` is the server's hint on when to try again.
+    Retry {
+        status: Option<StatusCode>,
+        message: String,
+        after: Option<Duration>,
+    },
+}
+
+/// Called before each retry with the retry number (starting at 1), why the previous attempt
+/// failed and how long the policy waits before trying again.
+pub type OnRetry = Arc<dyn Fn(u32, &str, Duration) + Send + Sync>;
+
+/// Exponential backoff with jitter.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Notified of every retry, e.g. to log it.
+    pub on_retry: Option<OnRetry>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("on_retry", &self.on_retry.is_some())
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            on_retry: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1), preferring the server's hint when it gave one.
+    fn delay(&self, retry: u32, hint: Option<Duration>) -> Duration {
+        if let Some(hint) = hint {
+            return hint.min(self.max_delay);
+        }
+        let exponential = self.base_delay.saturating_mul(1 << (retry - 1).min(16)).min(self.max_delay);
+        // Equal jitter: wait at least half the backoff so retries still spread out.
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+
+    /// Runs `operation` until it completes, fails permanently or runs out of retries.
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Attempt<T>>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await? {
+                Attempt::Done(value) => return Ok(value),
+                Attempt::Retry { status, message, after } => {
+                    if retry == self.max_retries {
+                        return Err(RetriesExhausted { attempts: retry + 1, status, message }.into());
+                    }
+                    retry += 1;
+                    let delay = self.delay(retry, after);
+                    if let Some(on_retry) = &self.on_retry {
+                        on_retry(retry, &message, delay);
+                    }
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// Whether a response with this status is worth retrying.
+pub fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parses a `Retry-After` header given in seconds.
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    parse_seconds(response.headers().get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+/// A number of seconds as a duration, or `None` if it is not a number, negative or too large
+/// for a `Duration`.
+pub fn parse_seconds(value: &str) -> Option<Duration> {
+    seconds(value.trim().parse().ok()?)
+}
+
+/// `seconds` as a duration, or `None` if it is negative, NaN or too large for a `Duration`.
+pub fn seconds(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Classifies a request that failed before a response arrived.
+pub fn transport_error<T>(error: reqwest::Error) -> Result<Attempt<T>> {
+    if error.is_timeout() || error.is_connect() || error.is_request() {
+        Ok(Attempt::Retry { status: None, message: error.to_string(), after: None })
+    } else {
+        Err(error.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy { max_retries: 5, base_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1), on_retry: None }
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_equal_jitter() {
+        let policy = policy();
+        for (retry, backoff) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (40, 1000)] {
+            let backoff = Duration::from_millis(backoff);
+            for _ in 0..100 {
+                let delay = policy.delay(retry, None);
+                assert!(delay >= backoff / 2 && delay <= backoff, "retry {}: {:?} outside {:?}", retry, delay, backoff);
+            }
+        }
+    }
+
+    #[test]
+    fn prefers_the_server_hint_up_to_the_maximum() {
+        let policy = policy();
+        assert_eq!(policy.delay(1, Some(Duration::from_millis(300))), Duration::from_millis(300));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(3600))), policy.max_delay);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_each_retry_to_the_callback() {
+        let retries = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = retries.clone();
+        let policy = RetryPolicy {
+            max_retries: 2,
+            on_retry: Some(Arc::new(move |retry, message: &str, delay| seen.lock().unwrap().push((retry, message.to_string(), delay)))),
+            ..policy()
+        };
+
+        let error = policy.run(|| async {
+            Ok::<_, anyhow::Error>(Attempt::<()>::Retry {
+                status: Some(StatusCode::SERVICE_UNAVAILABLE),
+                message: "busy".to_string(),
+                after: Some(Duration::from_millis(250)),
+            })
+        }).await.unwrap_err();
+
+        let exhausted = error.downcast_ref::<RetriesExhausted>().unwrap();
+        assert_eq!(exhausted.attempts, 3);
+        assert_eq!(*retries.lock().unwrap(), [
+            (1, "busy".to_string(), Duration::from_millis(250)),
+            (2, "busy".to_string(), Duration::from_millis(250)),
+        ]);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(parse_seconds(" 2 "), Some(Duration::from_secs(2)));
+        assert_eq!(parse_seconds("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_seconds("0"), Some(Duration::ZERO));
+        for invalid in ["-1", "NaN", "inf", "1e30", "Wed, 21 Oct 2015 07:28:00 GMT", ""] {
+            assert_eq!(parse_seconds(invalid), None, "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Binary file format of an embedded collection.
+//!
+//! A collection file is a 64-byte header followed by three segments, all little-endian:
+//!
+//! | segment  | contents                                                              |
+//! |----------|-----------------------------------------------------------------------|
+//! | ids      | `count` point ids of 17 bytes each, starting right after the header   |
+//! | vectors  | `count * dimension` values as `f32`, starting on a 64-byte boundary   |
+//! | payloads | JSON object holding the collection name and one payload per id        |
+//!
+//! An id is a kind byte followed by 16 bytes: 0 and a `u64` padded with zeros for numeric
+//! ids, 1 and the UUID bytes for UUIDs. Version 1 files hold plain `u64` ids instead.
+//!
+//! The whole file is read into memory on open. Only points are stored: the HNSW graph is
+//! rebuilt from the vectors every time a collection is loaded.
+//!
+//! Header fields by byte offset:
+//!
+//! | offset | type     | field                                              |
+//! |--------|----------|----------------------------------------------------|
+//! | 0      | `[u8;4]` | magic, `RVDB`                                      |
+//! | 4      | `u16`    | format version                                     |
+//! | 6      | `u8`     | metric: 1 cosine, 2 dot, 3 euclid, 4 manhattan     |
+//! | 8      | `u32`    | dimension                                          |
+//! | 16     | `u64`    | point count                                        |
+//! | 24     | `u64`    | offset of the vector segment                       |
+//! | 32     | `u64`    | offset of the payload segment                      |
+//! | 40     | `u64`    | length of the payload segment                      |
+//! | 48     | `u32`    | CRC-32 of the id segment                           |
+//! | 52     | `u32`    | CRC-32 of the vector segment                       |
+//! | 56     | `u32`    | CRC-32 of the payload segment                      |
+//! | 60     | `u32`    | CRC-32 of header bytes 0..60                       |
+//!
+//! Unlisted bytes are zero.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use uuid::Uuid;
+
+use super::{StoredCollection, StoredPoint};
+use crate::store::{Collection, Distance, PointId};
+
+pub const MAGIC: [u8; 4] = *b"RVDB";
+/// Version written by this build; files of any earlier version can still be read.
+pub const VERSION: u16 = 2;
+
+const HEADER_LEN: usize = 64;
+const VECTOR_ALIGN: usize = 64;
+
+#[derive(Serialize)]
+struct PayloadSegmentRef<'a> {
+    name: &'a str,
+    payloads: Vec<&'a serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+struct PayloadSegment {
+    name: String,
+    payloads: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Serializes a collection in the current format version.
+pub fn encode(collection: &StoredCollection) -> Result<Vec<u8>> {
+    let config = &collection.config;
+    let count = collection.points.len();
+
+    let mut ids = Vec::with_capacity(count * id_len(VERSION));
+    let mut vectors = Vec::with_capacity(count * config.vector_size * 4);
+    for point in &collection.points {
+        anyhow::ensure!(
+            point.vector.len() == config.vector_size,
+            "Point {} has {} dimensions, collection {} expects {}", point.id, point.vector.len(), config.name, config.vector_size
+        );
+        encode_id(point.id, &mut ids);
+        for value in &point.vector {
+            vectors.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let payloads = serde_json::to_vec(&PayloadSegmentRef {
+        name: &config.name,
+        payloads: collection.points.iter().map(|point| &point.payload).collect(),
+    })?;
+
+    let vectors_offset = (HEADER_LEN + ids.len()).next_multiple_of(VECTOR_ALIGN);
+    let payloads_offset = vectors_offset + vectors.len();
+
+    let mut header = [0u8; HEADER_LEN];
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..6].copy_from_slice(&VERSION.to_le_bytes());
+    header[6] = metric_code(config.distance);
+    header[8..12].copy_from_slice(&u32::try_from(config.vector_size)?.to_le_bytes());
+    header[16..24].copy_from_slice(&(count as u64).to_le_bytes());
+    header[24..32].copy_from_slice(&(vectors_offset as u64).to_le_bytes());
+    header[32..40].copy_from_slice(&(payloads_offset as u64).to_le_bytes());
+    header[40..48].copy_from_slice(&(payloads.len() as u64).to_le_bytes());
+    header[48..52].copy_from_slice(&crc32fast::hash(&ids).to_le_bytes());
+    header[52..56].copy_from_slice(&crc32fast::hash(&vectors).to_le_bytes());
+    header[56..60].copy_from_slice(&crc32fast::hash(&payloads).to_le_bytes());
+    let header_crc = crc32fast::hash(&header[..60]);
+    header[60..64].copy_from_slice(&header_crc.to_le_bytes());
+
+    let mut file = Vec::with_capacity(payloads_offset + payloads.len());
+    file.extend_from_slice(&header);
+    file.extend_from_slice(&ids);
+    file.resize(vectors_offset, 0);
+    file.extend_from_slice(&vectors);
+    file.extend_from_slice(&payloads);
+    Ok(file)
+}
+
+/// Reads a collection file of any supported version, verifying its checksums.
+pub fn decode(bytes: &[u8]) -> Result<StoredCollection> {
+    anyhow::ensure!(bytes.len() >= HEADER_LEN && bytes[0..4] == MAGIC, "Not a rust-vdb collection file");
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    match version {
+        1 | 2 => decode_binary(bytes, version),
+        _ if version > VERSION => anyhow::bail!(
+            "Collection file has format version {}, but this build only reads up to version {}; upgrade rust-vdb",
+            version, VERSION
+        ),
+        _ => anyhow::bail!("Unsupported collection format version {}", version),
+    }
+}
+
+fn decode_binary(bytes: &[u8], version: u16) -> Result<StoredCollection> {
+    let header = &bytes[..HEADER_LEN];
+    anyhow::ensure!(read_u32(header, 60) == crc32fast::hash(&header[..60]), "Collection header is corrupt (checksum mismatch)");
+
+    let distance = metric_from_code(header[6])?;
+    let dimension = read_u32(header, 8) as usize;
+    anyhow::ensure!(dimension > 0, "Collection header has a dimension of 0");
+    let count = usize::try_from(read_u64(header, 16))?;
+
+    let ids_len = (count as u64).checked_mul(id_len(version) as u64).context("Collection header is corrupt")?;
+    let vectors_len = (count as u64).checked_mul(dimension as u64 * 4).context("Collection header is corrupt")?;
+    let ids = segment(bytes, HEADER_LEN as u64, ids_len)?;
+    let vectors = segment(bytes, read_u64(header, 24), vectors_len)?;
+    let payloads = segment(bytes, read_u64(header, 32), read_u64(header, 40))?;
+    for (name, data, crc_offset) in [("id", ids, 48), ("vector", vectors, 52), ("payload", payloads, 56)] {
+        anyhow::ensure!(
+            crc32fast::hash(data) == read_u32(header, crc_offset),
+            "Collection {} segment is corrupt (checksum mismatch)", name
+        );
+    }
+
+    let payloads: PayloadSegment = serde_json::from_slice(payloads).context("Failed to parse the payload segment")?;
+    anyhow::ensure!(
+        payloads.payloads.len() == count,
+        "Payload segment holds {} payloads for {} points", payloads.payloads.len(), count
+    );
+
+    let points = ids.chunks_exact(id_len(version))
+        .zip(vectors.chunks_exact(dimension * 4))
+        .zip(payloads.payloads)
+        .map(|((id, vector), payload)| Ok(StoredPoint {
+            id: decode_id(id, version)?,
+            vector: vector.chunks_exact(4).map(|value| f32::from_le_bytes(value.try_into().unwrap())).collect(),
+            payload,
+        }))
+        .collect::<Result<_>>()?;
+
+    Ok(StoredCollection {
+        config: Collection { name: payloads.name, vector_size: dimension, distance },
+        points,
+    })
+}
+
+/// Bytes taken by one id in the id segment of `version`.
+fn id_len(version: u16) -> usize {
+    if version == 1 { 8 } else { 17 }
+}
+
+fn encode_id(id: PointId, out: &mut Vec<u8>) {
+    match id {
+        PointId::Num(id) => {
+            out.push(0);
+            out.extend_from_slice(&id.to_le_bytes());
+            out.extend_from_slice(&[0; 8]);
+        }
+        PointId::Uuid(id) => {
+            out.push(1);
+            out.extend_from_slice(id.as_bytes());
+        }
+    }
+}
+
+fn decode_id(bytes: &[u8], version: u16) -> Result<PointId> {
+    if version == 1 {
+        return Ok(PointId::Num(u64::from_le_bytes(bytes.try_into().unwrap())));
+    }
+    match bytes[0] {
+        0 => Ok(PointId::Num(u64::from_le_bytes(bytes[1..9].try_into().unwrap()))),
+        1 => Ok(PointId::Uuid(Uuid::from_bytes(bytes[1..17].try_into().unwrap()))),
+        kind => anyhow::bail!("Collection id segment has unknown id kind {}", kind),
+    }
+}
+
+/// The `len` bytes at `offset`, or an error if the file is too short for them.
+fn segment(bytes: &[u8], offset: u64, len: u64) -> Result<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(len).ok())
+        .and_then(|(offset, len)| bytes.get(offset..offset.checked_add(len)?))
+        .context("Collection file is truncated")
+}
+
+fn read_u32(header: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(header: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap())
+}
+
+fn metric_code(distance: Distance) -> u8 {
+    match distance {
+        Distance::Cosine => 1,
+        Distance::Dot => 2,
+        Distance::Euclid => 3,
+        Distance::Manhattan => 4,
+    }
+}
+
+fn metric_from_code(code: u8) -> Result<Distance> {
+    match code {
+        1 => Ok(Distance::Cosine),
+        2 => Ok(Distance::Dot),
+        3 => Ok(Distance::Euclid),
+        4 => Ok(Distance::Manhattan),
+        _ => anyhow::bail!("Collection header has unknown metric {}", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection() -> StoredCollection {
+        let payload = |text: &str| serde_json::Map::from_iter([("text".to_string(), serde_json::json!(text))]);
+        StoredCollection {
+            config: Collection { name: "docs".to_string(), vector_size: 3, distance: Distance::Euclid },
+            points: vec![
+                StoredPoint { id: PointId::Num(u64::MAX), vector: vec![1.0, -2.5, 0.0], payload: payload("first") },
+                StoredPoint {
+                    id: PointId::Uuid(Uuid::new_v5(&Uuid::NAMESPACE_OID, b"second")),
+                    vector: vec![0.25, 4.0, f32::MIN_POSITIVE],
+                    payload: payload("second"),
+                },
+            ],
+        }
+    }
+
+    fn assert_same(decoded: &StoredCollection, expected: &StoredCollection) {
+        assert_eq!(decoded.config.name, expected.config.name);
+        assert_eq!(decoded.config.vector_size, expected.config.vector_size);
+        assert_eq!(decoded.config.distance, expected.config.distance);
+        assert_eq!(decoded.points.len(), expected.points.len());
+        for (decoded, expected) in decoded.points.iter().zip(&expected.points) {
+            assert_eq!(decoded.id, expected.id);
+            assert_eq!(decoded.vector, expected.vector);
+            assert_eq!(decoded.payload, expected.payload);
+        }
+    }
+
+    #[test]
+    fn round_trips_a_collection() {
+        let expected = collection();
+        let bytes = encode(&expected).unwrap();
+        assert_eq!(read_u64(&bytes, 24) as usize % VECTOR_ALIGN, 0);
+        assert_same(&decode(&bytes).unwrap(), &expected);
+
+        let empty = StoredCollection { points: Vec::new(), ..collection() };
+        assert_same(&decode(&encode(&empty).unwrap()).unwrap(), &empty);
+    }
+
+    /// Rewrites a version 2 file with numeric ids as version 1, with 8-byte ids.
+    fn downgrade_to_v1(bytes: &[u8]) -> Vec<u8> {
+        let count = read_u64(bytes, 16) as usize;
+        let ids: Vec<u8> = bytes[HEADER_LEN..HEADER_LEN + count * 17]
+            .chunks_exact(17)
+            .flat_map(|id| id[1..9].to_vec())
+            .collect();
+        let vectors = &bytes[read_u64(bytes, 24) as usize..read_u64(bytes, 32) as usize];
+        let payloads = &bytes[read_u64(bytes, 32) as usize..];
+
+        let vectors_offset = (HEADER_LEN + ids.len()).next_multiple_of(VECTOR_ALIGN);
+        let mut header: [u8; HEADER_LEN] = bytes[..HEADER_LEN].try_into().unwrap();
+        header[4..6].copy_from_slice(&1u16.to_le_bytes());
+        header[24..32].copy_from_slice(&(vectors_offset as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&((vectors_offset + vectors.len()) as u64).to_le_bytes());
+        header[48..52].copy_from_slice(&crc32fast::hash(&ids).to_le_bytes());
+        let header_crc = crc32fast::hash(&header[..60]);
+        header[60..64].copy_from_slice(&header_crc.to_le_bytes());
+
+        let mut file = header.to_vec();
+        file.extend_from_slice(&ids);
+        file.resize(vectors_offset, 0);
+        file.extend_from_slice(vectors);
+        file.extend_from_slice(payloads);
+        file
+    }
+
+    #[test]
+    fn decodes_version_1_files() {
+        let mut expected = collection();
+        expected.points.truncate(1);
+        let decoded = decode(&downgrade_to_v1(&encode(&expected).unwrap())).unwrap();
+        assert_same(&decoded, &expected);
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let bytes = encode(&collection()).unwrap();
+        let vectors_offset = read_u64(&bytes, 24) as usize;
+        let payloads_offset = read_u64(&bytes, 32) as usize;
+        for (offset, segment) in [(8, "header"), (HEADER_LEN + 1, "id"), (vectors_offset, "vector"), (payloads_offset + 1, "payload")] {
+            let mut corrupt = bytes.clone();
+            corrupt[offset] ^= 0x40;
+            let error = decode(&corrupt).err().unwrap_or_else(|| panic!("corrupt {} segment was accepted", segment));
+            assert!(error.to_string().contains(&format!("{} is corrupt", segment))
+                || error.to_string().contains(&format!("{} segment is corrupt", segment)), "{}", error);
+        }
+
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(b"not a collection").is_err());
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(decode(&future).err().unwrap().to_string().contains("upgrade"));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+};
+
+use crate::store::{metric::distance, Distance};
+
+/// Hierarchical navigable small world graph over the vectors of a collection.
+///
+/// Nodes are slot indices into the collection's vector array, inserted in slot order.
+/// Deleted slots stay in the graph as routing points; callers skip them in results.
+pub struct Hnsw {
+    metric: Distance,
+    /// Links per node on the upper layers; the bottom layer allows twice as many.
+    m: usize,
+    ef_construction: usize,
+    level_multiplier: f64,
+    /// `links[node][layer]` are the neighbours of `node` on `layer`.
+    links: Vec<Vec<Vec<usize>>>,
+    entry_point: Option<usize>,
+    rng_state: u64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hnsw {
+    pub fn new(metric: Distance) -> Self {
+        let m = 16;
+        Self {
+            metric,
+            m,
+            ef_construction: 100,
+            level_multiplier: 1.0 / (m as f64).ln(),
+            links: Vec::new(),
+            entry_point: None,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Draws a layer from an exponentially decaying distribution.
+    fn random_level(&mut self) -> usize {
+        // xorshift64*: deterministic, so rebuilding an index from the same data gives the same graph.
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() * self.level_multiplier) as usize
+    }
+
+    fn top_layer(&self, node: usize) -> usize {
+        self.links[node].len() - 1
+    }
+
+    /// Adds `node`, which must be the next slot (`self.len()`), to the graph.
+    pub fn insert(&mut self, node: usize, vectors: &[Vec<f32>]) {
+        debug_assert_eq!(node, self.links.len());
+        let level = self.random_level();
+        self.links.push(vec![Vec::new(); level + 1]);
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            return;
+        };
+
+        let query = &vectors[node];
+        let top = self.top_layer(entry_point);
+        let mut nearest = vec![Candidate { distance: distance(self.metric, query, &vectors[entry_point]), node: entry_point }];
+
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(query, &nearest, 1, layer, vectors);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            nearest = self.search_layer(query, &nearest, self.ef_construction, layer, vectors);
+            let neighbours: Vec<usize> = nearest.iter().take(self.max_links(layer)).map(|c| c.node).collect();
+
+            for &neighbour in &neighbours {
+                self.links[neighbour][layer].push(node);
+                if self.links[neighbour][layer].len() > self.max_links(layer) {
+                    self.prune(neighbour, layer, vectors);
+                }
+            }
+            self.links[node][layer] = neighbours;
+        }
+
+        if level > top {
+            self.entry_point = Some(node);
+        }
+    }
+
+    /// Keeps only the closest links of `node` on `layer`.
+    fn prune(&mut self, node: usize, layer: usize, vectors: &[Vec<f32>]) {
+        let mut candidates: Vec<Candidate> = self.links[node][layer].iter()
+            .map(|&other| Candidate { distance: distance(self.metric, &vectors[node], &vectors[other]), node: other })
+            .collect();
+        candidates.sort();
+        candidates.truncate(self.max_links(layer));
+        self.links[node][layer] = candidates.into_iter().map(|c| c.node).collect();
+    }
+
+    /// Beam search on one layer, returning up to `ef` candidates sorted nearest first.
+    fn search_layer(&self, query: &[f32], entry_points: &[Candidate], ef: usize, layer: usize, vectors: &[Vec<f32>]) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().map(|c| c.node).collect();
+        let mut frontier: BinaryHeap<Reverse<Candidate>> = entry_points.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Candidate> = entry_points.iter().copied().collect();
+
+        while let Some(Reverse(current)) = frontier.pop() {
+            let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+            if current.distance > furthest && found.len() >= ef {
+                break;
+            }
+            let Some(neighbours) = self.links[current.node].get(layer) else { continue };
+            for &neighbour in neighbours {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = Candidate { distance: distance(self.metric, query, &vectors[neighbour]), node: neighbour };
+                let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+                if found.len() < ef || candidate.distance < furthest {
+                    frontier.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Approximate nearest neighbours of `query` as `(slot, distance)`, nearest first.
+    /// Returns up to `ef` nodes, which may include deleted slots.
+    pub fn search(&self, query: &[f32], ef: usize, vectors: &[Vec<f32>]) -> Vec<(usize, f32)> {
+        let Some(entry_point) = self.entry_point else {
+            return Vec::new();
+        };
+        let mut nearest = vec![Candidate { distance: distance(self.metric, query, &vectors[entry_point]), node: entry_point }];
+        for layer in (1..=self.top_layer(entry_point)).rev() {
+            nearest = self.search_layer(query, &nearest, 1, layer, vectors);
+        }
+        self.search_layer(query, &nearest, ef, 0, vectors)
+            .into_iter()
+            .map(|c| (c.node, c.distance))
+            .collect()
+    }
+}
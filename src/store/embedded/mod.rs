@@ -0,0 +1,504 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::RwLock,
+};
+
+mod format;
+mod hnsw;
+
+use hnsw::Hnsw;
+use super::metric;
+use super::{Collection, Distance, Point, PointId, Record, ScrollPage, SearchRequest, SearchResultItem, VectorStore};
+
+/// How the embedded store answers searches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexKind {
+    /// Exact search comparing the query with every vector.
+    Flat,
+    /// Approximate search through an HNSW graph, much faster on large collections.
+    #[default]
+    Hnsw,
+}
+
+impl FromStr for IndexKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "flat" => Ok(IndexKind::Flat),
+            "hnsw" => Ok(IndexKind::Hnsw),
+            _ => anyhow::bail!("Unknown index {}, expected flat or hnsw", s),
+        }
+    }
+}
+
+/// Candidates examined per HNSW search; higher is slower but closer to exact.
+const EF_SEARCH: usize = 64;
+
+/// In-process vector store kept in memory and persisted to a local directory,
+/// one subdirectory per collection. Changes are written on [`VectorStore::flush`] and when
+/// the store is dropped.
+pub struct EmbeddedStore {
+    dir: PathBuf,
+    index: IndexKind,
+    collections: RwLock<HashMap<String, CollectionData>>,
+}
+
+/// Points of one collection, stored in slots. Replaced and deleted points leave an empty
+/// slot behind so that slot numbers in the HNSW graph stay valid.
+struct CollectionData {
+    config: Collection,
+    ids: Vec<PointId>,
+    vectors: Vec<Vec<f32>>,
+    payloads: Vec<serde_json::Map<String, serde_json::Value>>,
+    live: Vec<bool>,
+    /// Slot of every live point, ordered by id for scrolling.
+    slots: BTreeMap<PointId, usize>,
+    hnsw: Option<Hnsw>,
+    /// Changed since the collection file was last written.
+    dirty: bool,
+}
+
+/// Contents of a collection file, see [`format`].
+struct StoredCollection {
+    config: Collection,
+    points: Vec<StoredPoint>,
+}
+
+struct StoredPoint {
+    id: PointId,
+    vector: Vec<f32>,
+    payload: serde_json::Map<String, serde_json::Value>,
+}
+
+const COLLECTION_FILE: &str = "collection.vdb";
+
+impl EmbeddedStore {
+    /// Opens the store in `dir`, loading every collection found there.
+    pub fn open(dir: impl AsRef<Path>, index: IndexKind) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let mut collections = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let collection_dir = entry?.path();
+            let Some(stored) = Self::load(&collection_dir)? else {
+                continue;
+            };
+            check_name(&stored.config.name).with_context(|| format!("Failed to load {}", collection_dir.display()))?;
+            let data = CollectionData::from_stored(stored, index);
+            collections.insert(data.config.name.clone(), data);
+        }
+
+        Ok(Self { dir, index, collections: RwLock::new(collections) })
+    }
+
+    /// Reads the collection in `dir`, if there is one.
+    fn load(dir: &Path) -> Result<Option<StoredCollection>> {
+        let path = dir.join(COLLECTION_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let stored = format::decode(&bytes).with_context(|| format!("Failed to load {}", path.display()))?;
+        Ok(Some(stored))
+    }
+
+    fn collection_dir(&self, name: &str) -> Result<PathBuf> {
+        check_name(name)?;
+        Ok(self.dir.join(name))
+    }
+}
+
+/// Writes changes that were not flushed yet, so that dropping the store never loses them.
+impl Drop for EmbeddedStore {
+    fn drop(&mut self) {
+        let Ok(collections) = self.collections.get_mut() else { return };
+        for data in collections.values().filter(|data| data.dirty) {
+            if let Err(error) = save(&self.dir.join(&data.config.name), &data.to_stored()) {
+                eprintln!("Failed to save collection {}: {:#}", data.config.name, error);
+            }
+        }
+    }
+}
+
+/// Collection names become directory names, so they are limited to letters, digits, `_` and `-`.
+fn check_name(name: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+        "Invalid collection name {:?}: use only ASCII letters, digits, _ and -", name
+    );
+    Ok(())
+}
+
+/// Writes a collection to `dir`, replacing the previous file atomically.
+fn save(dir: &Path, collection: &StoredCollection) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomic(&dir.join(COLLECTION_FILE), &format::encode(collection)?)
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it into place.
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, bytes).with_context(|| format!("Failed to write {}", temporary.display()))?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+impl CollectionData {
+    fn new(config: Collection, index: IndexKind) -> Self {
+        let hnsw = (index == IndexKind::Hnsw).then(|| Hnsw::new(config.distance));
+        Self {
+            config,
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            payloads: Vec::new(),
+            live: Vec::new(),
+            slots: BTreeMap::new(),
+            hnsw,
+            dirty: false,
+        }
+    }
+
+    fn from_stored(stored: StoredCollection, index: IndexKind) -> Self {
+        let mut data = Self::new(stored.config, index);
+        for point in stored.points {
+            data.insert(point.id, point.vector, point.payload);
+        }
+        data
+    }
+
+    fn to_stored(&self) -> StoredCollection {
+        StoredCollection {
+            config: self.config.clone(),
+            points: self.slots.iter()
+                .map(|(&id, &slot)| StoredPoint {
+                    id,
+                    vector: self.vectors[slot].clone(),
+                    payload: self.payloads[slot].clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn insert(&mut self, id: PointId, mut vector: Vec<f32>, payload: serde_json::Map<String, serde_json::Value>) {
+        if self.config.distance == Distance::Cosine {
+            metric::normalize(&mut vector);
+        }
+        self.remove(id);
+
+        let slot = self.ids.len();
+        self.ids.push(id);
+        self.vectors.push(vector);
+        self.payloads.push(payload);
+        self.live.push(true);
+        self.slots.insert(id, slot);
+        if let Some(hnsw) = &mut self.hnsw {
+            hnsw.insert(slot, &self.vectors);
+        }
+    }
+
+    fn remove(&mut self, id: PointId) {
+        if let Some(slot) = self.slots.remove(&id) {
+            self.live[slot] = false;
+            self.payloads[slot] = serde_json::Map::new();
+        }
+    }
+
+    /// The `limit` nearest live points to `query` as `(slot, distance)`, nearest first.
+    fn nearest(&self, query: &[f32], limit: usize, request: &SearchRequest) -> Vec<(usize, f32)> {
+        let matches = |slot: usize| {
+            self.live[slot] && request.filter.as_ref().is_none_or(|filter| filter.matches(&self.payloads[slot]))
+        };
+
+        // A selective filter can leave the graph neighbourhood empty, so filtered searches scan.
+        if let (Some(hnsw), None) = (&self.hnsw, &request.filter) {
+            let mut found: Vec<(usize, f32)> = hnsw.search(query, EF_SEARCH.max(limit), &self.vectors)
+                .into_iter()
+                .filter(|&(slot, _)| matches(slot))
+                .collect();
+            if found.len() >= limit.min(self.slots.len()) {
+                found.truncate(limit);
+                return found;
+            }
+        }
+
+        let mut found: Vec<(usize, f32)> = self.slots.values()
+            .copied()
+            .filter(|&slot| matches(slot))
+            .map(|slot| (slot, metric::distance(self.config.distance, query, &self.vectors[slot])))
+            .collect();
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.truncate(limit);
+        found
+    }
+}
+
+#[async_trait]
+impl VectorStore for EmbeddedStore {
+    async fn create_collection(&self, collection: &Collection) -> Result<()> {
+        check_name(&collection.name)?;
+        let mut collections = self.collections.write().unwrap();
+        anyhow::ensure!(!collections.contains_key(&collection.name), "Collection {} already exists", collection.name);
+
+        let mut data = CollectionData::new(collection.clone(), self.index);
+        data.dirty = true;
+        collections.insert(collection.name.clone(), data);
+        Ok(())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        let dir = self.collection_dir(name)?;
+        let mut collections = self.collections.write().unwrap();
+        collections.remove(name).with_context(|| format!("Collection {} does not exist", name))?;
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, points: Point) -> Result<()> {
+        let mut collections = self.collections.write().unwrap();
+        let data = collections.get_mut(collection).with_context(|| format!("Collection {} does not exist", collection))?;
+
+        points.check(collection, data.config.vector_size)?;
+
+        let mut payloads = points.payloads.unwrap_or_default().into_iter();
+        for (id, vector) in points.ids.into_iter().zip(points.vectors) {
+            let payload = payloads.next().unwrap_or_default().into_iter().collect();
+            data.insert(id, vector, payload);
+        }
+        data.dirty = true;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[PointId]) -> Result<()> {
+        let mut collections = self.collections.write().unwrap();
+        let data = collections.get_mut(collection).with_context(|| format!("Collection {} does not exist", collection))?;
+        for &id in ids {
+            data.remove(id);
+        }
+        data.dirty = true;
+        Ok(())
+    }
+
+    async fn search(&self, collection: &str, request: SearchRequest) -> Result<Vec<SearchResultItem>> {
+        let collections = self.collections.read().unwrap();
+        let data = collections.get(collection).with_context(|| format!("Collection {} does not exist", collection))?;
+        anyhow::ensure!(
+            request.vector.len() == data.config.vector_size,
+            "Query has {} dimensions, collection {} expects {}", request.vector.len(), collection, data.config.vector_size
+        );
+
+        let mut query = request.vector.clone();
+        if data.config.distance == Distance::Cosine {
+            metric::normalize(&mut query);
+        }
+        let metric = data.config.distance;
+        Ok(data.nearest(&query, request.offset + request.limit, &request)
+            .into_iter()
+            .skip(request.offset)
+            .map(|(slot, distance)| (slot, metric::score(metric, distance)))
+            .filter(|&(_, score)| request.score_threshold.is_none_or(|threshold| metric::within_threshold(metric, score, threshold)))
+            .map(|(slot, score)| SearchResultItem {
+                id: data.ids[slot],
+                score,
+                payload: Some(data.payloads[slot].clone()),
+                vector: request.with_vectors.then(|| serde_json::json!(data.vectors[slot])),
+            })
+            .collect())
+    }
+
+    async fn count(&self, collection: &str) -> Result<u64> {
+        let collections = self.collections.read().unwrap();
+        let data = collections.get(collection).with_context(|| format!("Collection {} does not exist", collection))?;
+        Ok(data.slots.len() as u64)
+    }
+
+    async fn scroll(&self, collection: &str, limit: usize, offset: Option<PointId>) -> Result<ScrollPage> {
+        let collections = self.collections.read().unwrap();
+        let data = collections.get(collection).with_context(|| format!("Collection {} does not exist", collection))?;
+
+        let mut page = match offset {
+            Some(offset) => data.slots.range(offset..),
+            None => data.slots.range(..),
+        };
+        let points = page.by_ref()
+            .take(limit)
+            .map(|(&id, &slot)| Record { id, payload: Some(data.payloads[slot].clone()), vector: None })
+            .collect();
+        let next_page_offset = page.next().map(|(&id, _)| id);
+        Ok(ScrollPage { points, next_page_offset })
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self.collections.read().unwrap().keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Rewrites the file of every collection changed since the last flush, off the async runtime.
+    async fn flush(&self) -> Result<()> {
+        let changed: Vec<(String, StoredCollection)> = self.collections.write().unwrap()
+            .values_mut()
+            .filter(|data| data.dirty)
+            .map(|data| {
+                data.dirty = false;
+                (data.config.name.clone(), data.to_stored())
+            })
+            .collect();
+        let names: Vec<String> = changed.iter().map(|(name, _)| name.clone()).collect();
+        let dir = self.dir.clone();
+        let saved = tokio::task::spawn_blocking(move || {
+            changed.into_iter()
+                .map(|(name, collection)| save(&dir.join(&name), &collection).map_err(|error| (name, error)))
+                .filter_map(Result::err)
+                .collect::<Vec<_>>()
+        }).await;
+
+        // Collections that were not saved stay dirty, so a later flush or the drop retries them.
+        let mut collections = self.collections.write().unwrap();
+        let failed: Vec<String> = match &saved {
+            Ok(errors) => errors.iter().map(|(name, _)| name.clone()).collect(),
+            Err(_) => names,
+        };
+        for name in &failed {
+            if let Some(data) = collections.get_mut(name) {
+                data.dirty = true;
+            }
+        }
+        match saved?.into_iter().next() {
+            Some((name, error)) => Err(error.context(format!("Failed to save collection {}", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(name: &str) -> Collection {
+        Collection { name: name.to_string(), vector_size: 2, distance: Distance::Dot }
+    }
+
+    fn points(ids: &[u64]) -> Point {
+        Point {
+            ids: ids.iter().map(|&id| PointId::Num(id)).collect(),
+            vectors: ids.iter().map(|&id| vec![id as f32, 1.0]).collect(),
+            payloads: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_collection_names_outside_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path().join("store"), IndexKind::Flat).unwrap();
+        for name in ["", "..", "../outside", "a/b", "a\\b", "with space"] {
+            assert!(store.create_collection(&collection(name)).await.is_err(), "{:?}", name);
+            assert!(store.drop_collection(name).await.is_err(), "{:?}", name);
+        }
+        store.create_collection(&collection("docs_v2-1")).await.unwrap();
+        assert!(!dir.path().join("outside").exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_points_and_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap();
+        store.create_collection(&collection("docs")).await.unwrap();
+
+        let mut extra_id = points(&[1, 2]);
+        extra_id.vectors.pop();
+        assert!(store.upsert("docs", extra_id).await.is_err());
+        let mut missing_payload = points(&[1, 2]);
+        missing_payload.payloads = Some(vec![HashMap::new()]);
+        assert!(store.upsert("docs", missing_payload).await.is_err());
+        let mut wrong_size = points(&[1, 2]);
+        wrong_size.vectors[1].push(0.0);
+        assert!(store.upsert("docs", wrong_size).await.is_err());
+        assert_eq!(store.count("docs").await.unwrap(), 0);
+
+        store.upsert("docs", points(&[1, 2])).await.unwrap();
+        assert!(store.search("docs", SearchRequest::new(vec![1.0, 0.0, 0.0], 1)).await.is_err());
+        assert_eq!(store.search("docs", SearchRequest::new(vec![1.0, 0.0], 1)).await.unwrap()[0].id, PointId::Num(2));
+    }
+
+    #[tokio::test]
+    async fn writes_changes_on_flush_and_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), IndexKind::Hnsw).unwrap();
+        store.create_collection(&collection("docs")).await.unwrap();
+        store.upsert("docs", points(&[1, 2, 3])).await.unwrap();
+        assert!(!dir.path().join("docs").join(COLLECTION_FILE).exists());
+        store.flush().await.unwrap();
+        assert_eq!(EmbeddedStore::open(dir.path(), IndexKind::Hnsw).unwrap().count("docs").await.unwrap(), 3);
+
+        store.delete("docs", &[PointId::Num(2)]).await.unwrap();
+        drop(store);
+        let reopened = EmbeddedStore::open(dir.path(), IndexKind::Hnsw).unwrap();
+        let page = reopened.scroll("docs", 10, None).await.unwrap();
+        assert_eq!(page.points.iter().map(|point| point.id).collect::<Vec<_>>(), [PointId::Num(1), PointId::Num(3)]);
+    }
+
+    #[tokio::test]
+    async fn keeps_changes_dirty_when_a_flush_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap();
+        store.create_collection(&collection("docs")).await.unwrap();
+        store.upsert("docs", points(&[1, 2, 3])).await.unwrap();
+
+        // A file where the collection directory belongs makes the save fail.
+        let blocker = dir.path().join("docs");
+        fs::write(&blocker, b"").unwrap();
+        assert!(store.flush().await.is_err());
+        assert!(store.collections.read().unwrap()["docs"].dirty);
+
+        fs::remove_file(&blocker).unwrap();
+        drop(store);
+        assert_eq!(EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap().count("docs").await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn applies_offset_threshold_and_with_vectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), IndexKind::Flat).unwrap();
+        let ids = |hits: &[SearchResultItem]| hits.iter().map(|hit| hit.id).collect::<Vec<_>>();
+        let numbered = |vectors: Vec<Vec<f32>>| Point {
+            ids: (1..=vectors.len() as u64).map(PointId::Num).collect(),
+            vectors,
+            payloads: None,
+        };
+        for (name, distance) in [("cosine", Distance::Cosine), ("euclid", Distance::Euclid), ("dot", Distance::Dot)] {
+            store.create_collection(&Collection { name: name.to_string(), vector_size: 2, distance }).await.unwrap();
+        }
+
+        store.upsert("cosine", numbered(vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]])).await.unwrap();
+        let query = || SearchRequest::new(vec![1.0, 0.0], 3);
+        let skipped = store.search("cosine", SearchRequest { offset: 1, ..query() }).await.unwrap();
+        assert_eq!(ids(&skipped), [PointId::Num(2), PointId::Num(3)]);
+        let similar = store.search("cosine", SearchRequest { score_threshold: Some(0.5), ..query() }).await.unwrap();
+        assert_eq!(ids(&similar), [PointId::Num(1), PointId::Num(2)]);
+        assert!(similar.iter().all(|hit| hit.score >= 0.5));
+
+        store.upsert("euclid", numbered(vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![3.0, 0.0]])).await.unwrap();
+        let request = SearchRequest { score_threshold: Some(1.5), ..SearchRequest::new(vec![0.0, 0.0], 3) };
+        let close = store.search("euclid", request).await.unwrap();
+        assert_eq!(ids(&close), [PointId::Num(1), PointId::Num(2)]);
+        assert!(close.iter().all(|hit| hit.score <= 1.5));
+
+        store.upsert("dot", numbered(vec![vec![3.0, 4.0]])).await.unwrap();
+        let without = store.search("dot", SearchRequest::new(vec![1.0, 0.0], 1)).await.unwrap();
+        assert_eq!(without[0].vector, None);
+        let with = store.search("dot", SearchRequest { with_vectors: true, ..SearchRequest::new(vec![1.0, 0.0], 1) }).await.unwrap();
+        assert_eq!(with[0].vector, Some(serde_json::json!([3.0, 4.0])));
+    }
+}
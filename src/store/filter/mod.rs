@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+mod parse;
+
+/// Restricts a search to points whose payload satisfies every condition in `must`, at least one
+/// in `should` (when there are any) and none in `must_not`.
+///
+/// Serializes to Qdrant's filter JSON, e.g. `{"must": [{"key": "source", "match": {"value": "reg-all.txt"}}]}`,
+/// and parses from the mini-language described at [`Filter::from_str`].
+///
+/// Keys address nested payload fields with dots, `meta.year`, and the objects of an array with
+/// `[]`, `sections[].title`. A condition on a key holds if it holds for any of its values,
+/// including the elements of an array.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Filter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must: Vec<Condition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should: Vec<Condition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must_not: Vec<Condition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    /// The key is missing, `null` or an empty array: `{"is_empty": {"key": "section"}}`.
+    IsEmpty { is_empty: FieldKey },
+    /// A match or range on the values of a key.
+    Field(FieldCondition),
+    /// A nested filter, for combining `must`, `should` and `must_not`.
+    Filter(Filter),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldKey {
+    pub key: String,
+}
+
+/// `{"key": "year", "match": {"value": 2023}}` or `{"key": "year", "range": {"gte": 2023}}`.
+/// When both are given, both must hold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldCondition {
+    pub key: String,
+    #[serde(rename = "match", default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<MatchValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MatchValue {
+    /// Equal to `value`.
+    Value { value: serde_json::Value },
+    /// Equal to any of `any`.
+    Any { any: Vec<serde_json::Value> },
+}
+
+/// Numeric bounds; unset bounds are open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gt: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gte: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lt: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lte: Option<f64>,
+}
+
+impl Filter {
+    /// A filter on a single payload field.
+    pub fn must_match(key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Self::default().must(Condition::equals(key, value))
+    }
+
+    pub fn must(mut self, condition: impl Into<Condition>) -> Self {
+        self.must.push(condition.into());
+        self
+    }
+
+    pub fn should(mut self, condition: impl Into<Condition>) -> Self {
+        self.should.push(condition.into());
+        self
+    }
+
+    pub fn must_not(mut self, condition: impl Into<Condition>) -> Self {
+        self.must_not.push(condition.into());
+        self
+    }
+
+    /// Evaluates the filter against a payload, for stores that filter in process.
+    pub fn matches(&self, payload: &serde_json::Map<String, serde_json::Value>) -> bool {
+        self.must.iter().all(|condition| condition.matches(payload))
+            && (self.should.is_empty() || self.should.iter().any(|condition| condition.matches(payload)))
+            && !self.must_not.iter().any(|condition| condition.matches(payload))
+    }
+}
+
+/// Parses the filter mini-language: comparisons joined with `AND`, `OR` and `NOT`, grouped
+/// with parentheses, e.g. `source = "reg-all.txt" AND (year >= 2023 OR tags IN ("faq", "policy"))`.
+///
+/// Comparisons are `key = value`, `key != value`, `<`, `<=`, `>`, `>=` against numbers,
+/// `key IN (value, ...)`, `key IS EMPTY` and `key IS NOT EMPTY`. Values are quoted strings,
+/// numbers, `true` and `false`.
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match parse::parse(s)? {
+            Condition::Filter(filter) => filter,
+            condition => Filter::default().must(condition),
+        })
+    }
+}
+
+impl From<Filter> for Condition {
+    fn from(filter: Filter) -> Self {
+        Condition::Filter(filter)
+    }
+}
+
+impl From<FieldCondition> for Condition {
+    fn from(condition: FieldCondition) -> Self {
+        Condition::Field(condition)
+    }
+}
+
+impl Condition {
+    /// The key equals `value`. Qdrant only matches keywords, integers and booleans, so a
+    /// fractional number becomes the range from `value` to `value`.
+    pub fn equals(key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        let value = value.into();
+        if let Some(number) = fractional(&value) {
+            return Condition::range(key, Range { gte: Some(number), lte: Some(number), ..Range::default() });
+        }
+        Condition::Field(FieldCondition { key: key.into(), matches: Some(MatchValue::Value { value }), range: None })
+    }
+
+    /// The key equals one of `values`; with fractional numbers among them, one of several
+    /// [`equals`](Condition::equals) conditions.
+    pub fn any(key: impl Into<String>, values: Vec<serde_json::Value>) -> Self {
+        let key = key.into();
+        if values.iter().any(|value| fractional(value).is_some()) {
+            let should = values.into_iter().map(|value| Condition::equals(key.clone(), value)).collect();
+            return Condition::Filter(Filter { should, ..Filter::default() });
+        }
+        Condition::Field(FieldCondition { key, matches: Some(MatchValue::Any { any: values }), range: None })
+    }
+
+    /// The key is a number within `range`.
+    pub fn range(key: impl Into<String>, range: Range) -> Self {
+        Condition::Field(FieldCondition { key: key.into(), matches: None, range: Some(range) })
+    }
+
+    /// The key is missing, `null` or an empty array.
+    pub fn is_empty(key: impl Into<String>) -> Self {
+        Condition::IsEmpty { is_empty: FieldKey { key: key.into() } }
+    }
+
+    pub fn matches(&self, payload: &serde_json::Map<String, serde_json::Value>) -> bool {
+        match self {
+            Condition::IsEmpty { is_empty } => values_at(payload, &is_empty.key).iter().all(|value| value.is_null()),
+            Condition::Field(condition) => {
+                let values = values_at(payload, &condition.key);
+                let matched = condition.matches.as_ref().is_none_or(|matches| match matches {
+                    MatchValue::Value { value } => values.contains(&value),
+                    MatchValue::Any { any } => values.iter().any(|value| any.contains(value)),
+                });
+                let in_range = condition.range.is_none_or(|range| {
+                    values.iter().filter_map(|value| value.as_f64()).any(|number| range.contains(number))
+                });
+                matched && in_range
+            }
+            Condition::Filter(filter) => filter.matches(payload),
+        }
+    }
+}
+
+impl Range {
+    pub fn contains(&self, number: f64) -> bool {
+        self.gt.is_none_or(|bound| number > bound)
+            && self.gte.is_none_or(|bound| number >= bound)
+            && self.lt.is_none_or(|bound| number < bound)
+            && self.lte.is_none_or(|bound| number <= bound)
+    }
+}
+
+/// The value of a number that is neither a signed nor an unsigned integer.
+fn fractional(value: &serde_json::Value) -> Option<f64> {
+    value.as_number().filter(|number| number.is_f64()).and_then(serde_json::Number::as_f64)
+}
+
+/// SQL for `filter` in a store that filters in the database: each condition is rendered by the
+/// backend's `condition_sql`, which binds its values in `params`, and `always` is the backend's
+/// literal for a filter without conditions.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub(crate) fn filter_sql<P>(filter: &Filter, params: &mut P, always: &str, condition_sql: fn(&Condition, &mut P) -> String) -> String {
+    let mut parts = Vec::new();
+    for condition in &filter.must {
+        parts.push(condition_sql(condition, params));
+    }
+    if !filter.should.is_empty() {
+        let any: Vec<String> = filter.should.iter().map(|condition| condition_sql(condition, params)).collect();
+        parts.push(format!("({})", any.join(" OR ")));
+    }
+    if !filter.must_not.is_empty() {
+        let any: Vec<String> = filter.must_not.iter().map(|condition| condition_sql(condition, params)).collect();
+        parts.push(format!("NOT ({})", any.join(" OR ")));
+    }
+    if parts.is_empty() {
+        always.to_string()
+    } else {
+        format!("({})", parts.join(" AND "))
+    }
+}
+
+/// The segments of a key: `sections[].title` is `[("sections", true), ("title", false)]`,
+/// where `true` marks a segment holding an array whose elements the rest of the key applies to.
+pub(crate) fn key_segments(key: &str) -> Vec<(&str, bool)> {
+    key.split('.')
+        .map(|segment| match segment.strip_suffix("[]") {
+            Some(name) => (name, true),
+            None => (segment, false),
+        })
+        .collect()
+}
+
+/// Every value the key points to, with arrays at the end of the key flattened into their elements.
+fn values_at<'a>(payload: &'a serde_json::Map<String, serde_json::Value>, key: &str) -> Vec<&'a serde_json::Value> {
+    fn flatten(values: Vec<&serde_json::Value>) -> Vec<&serde_json::Value> {
+        values.into_iter()
+            .flat_map(|value| match value {
+                serde_json::Value::Array(items) => items.iter().collect(),
+                other => vec![other],
+            })
+            .collect()
+    }
+
+    let mut values: Vec<&serde_json::Value> = Vec::new();
+    for (index, (name, array)) in key_segments(key).into_iter().enumerate() {
+        let next: Vec<&serde_json::Value> = if index == 0 {
+            payload.get(name).into_iter().collect()
+        } else {
+            values.iter().filter_map(|&value| value.get(name)).collect()
+        };
+        values = if array { flatten(next) } else { next };
+    }
+    flatten(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matches(filter: &str, payload: serde_json::Value) -> bool {
+        let serde_json::Value::Object(payload) = payload else { panic!("payload must be an object") };
+        filter.parse::<Filter>().unwrap().matches(&payload)
+    }
+
+    #[test]
+    fn evaluates_parsed_filters() {
+        let payload = json!({
+            "source": "reg-all.txt",
+            "price": 1.5,
+            "meta": { "year": 2024 },
+            "tags": ["faq", "policy"],
+            "sections": [{ "title": "Scope" }, { "title": "Holds" }],
+            "empty": [],
+        });
+        assert!(matches(r#"source = "reg-all.txt" AND meta.year >= 2023"#, payload.clone()));
+        assert!(matches("price = 1.5 AND price IN (1.5, 3)", payload.clone()));
+        assert!(!matches("price = 1.25", payload.clone()));
+        assert!(matches(r#"tags = "policy" AND sections[].title = "Holds""#, payload.clone()));
+        assert!(matches("empty IS EMPTY AND missing IS EMPTY AND tags IS NOT EMPTY", payload.clone()));
+        assert!(!matches(r#"NOT tags IN ("faq") OR meta.year < 2000"#, payload));
+    }
+
+    #[test]
+    fn serializes_to_qdrant_filters() {
+        let filter: Filter = r#"source = "a.txt" AND NOT price = 1.5"#.parse().unwrap();
+        assert_eq!(serde_json::to_value(&filter).unwrap(), json!({
+            "must": [
+                { "key": "source", "match": { "value": "a.txt" } },
+                { "must_not": [{ "key": "price", "range": { "gte": 1.5, "lte": 1.5 } }] },
+            ],
+        }));
+    }
+}
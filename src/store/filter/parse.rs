@@ -0,0 +1,330 @@
+//! The `--filter` mini-language, parsed by recursive descent:
+//!
+//! ```text
+//! or         := and ("OR" and)*
+//! and        := unary ("AND" unary)*
+//! unary      := "NOT" unary | "(" or ")" | comparison
+//! comparison := key ("=" | "!=") value
+//!             | key ("<" | "<=" | ">" | ">=") number
+//!             | key "IN" "(" value ("," value)* ")"
+//!             | key "IS" ["NOT"] "EMPTY"
+//! ```
+//!
+//! Keywords are case-insensitive.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use super::{Condition, Filter, Range};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Number(serde_json::Number),
+    Operator(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let followed_by_equals = chars.next_if(|&(_, next)| next == '=').is_some();
+                tokens.push(Token::Operator(match (c, followed_by_equals) {
+                    ('=', _) => "=",
+                    ('!', true) => "!=",
+                    ('<', false) => "<",
+                    ('<', true) => "<=",
+                    ('>', false) => ">",
+                    ('>', true) => ">=",
+                    _ => anyhow::bail!("Expected != at position {}", start),
+                }));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => anyhow::bail!("Unterminated string starting at position {}", start),
+                        },
+                        Some((_, quote)) if quote == c => break,
+                        Some((_, other)) => text.push(other),
+                        None => anyhow::bail!("Unterminated string starting at position {}", start),
+                    }
+                }
+                tokens.push(Token::String(text));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let mut end = start;
+                while let Some((index, next)) = chars.next_if(|&(_, next)| {
+                    next.is_ascii_alphanumeric() || matches!(next, '.' | '-' | '+')
+                }) {
+                    end = index + next.len_utf8();
+                }
+                let literal = &input[start..end];
+                let number = serde_json::from_str(literal.trim_start_matches('+'))
+                    .with_context(|| format!("Invalid number {}", literal))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut end = start;
+                while let Some((index, next)) = chars.next_if(|&(_, next)| {
+                    next.is_alphanumeric() || matches!(next, '_' | '-' | '.' | '[' | ']')
+                }) {
+                    end = index + next.len_utf8();
+                }
+                tokens.push(Token::Word(input[start..end].to_string()));
+            }
+            _ => anyhow::bail!("Unexpected {:?} at position {}", c, start),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consumes the next token if it is the keyword `keyword`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => anyhow::bail!("Expected {:?}, found {:?}", expected, token),
+            None => anyhow::bail!("Expected {:?}, found end of filter", expected),
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.and()?];
+        while self.keyword("or") {
+            conditions.push(self.and()?);
+        }
+        Ok(if conditions.len() == 1 {
+            conditions.pop().unwrap()
+        } else {
+            Condition::Filter(Filter { should: conditions, ..Filter::default() })
+        })
+    }
+
+    fn and(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.unary()?];
+        while self.keyword("and") {
+            conditions.push(self.unary()?);
+        }
+        Ok(if conditions.len() == 1 {
+            conditions.pop().unwrap()
+        } else {
+            Condition::Filter(Filter { must: conditions, ..Filter::default() })
+        })
+    }
+
+    fn unary(&mut self) -> Result<Condition> {
+        if self.keyword("not") {
+            return Ok(Condition::Filter(Filter::default().must_not(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let condition = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(condition);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Condition> {
+        let key = match self.next() {
+            Some(Token::Word(key)) => key,
+            Some(token) => anyhow::bail!("Expected a payload key, found {:?}", token),
+            None => anyhow::bail!("Expected a payload key, found end of filter"),
+        };
+
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            anyhow::ensure!(self.keyword("empty"), "Expected EMPTY after {} IS", key);
+            let condition = Condition::is_empty(key);
+            return Ok(if negated { Condition::Filter(Filter::default().must_not(condition)) } else { condition });
+        }
+
+        if self.keyword("in") {
+            self.expect(Token::Open)?;
+            let mut values = vec![self.value()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                values.push(self.value()?);
+            }
+            self.expect(Token::Close)?;
+            return Ok(Condition::any(key, values));
+        }
+
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => operator,
+            Some(token) => anyhow::bail!("Expected a comparison after {}, found {:?}", key, token),
+            None => anyhow::bail!("Expected a comparison after {}, found end of filter", key),
+        };
+        if operator == "=" || operator == "!=" {
+            let condition = Condition::equals(key, self.value()?);
+            return Ok(if operator == "=" { condition } else { Condition::Filter(Filter::default().must_not(condition)) });
+        }
+
+        let bound = match self.value()? {
+            Value::Number(number) => number.as_f64().context("Number out of range")?,
+            other => anyhow::bail!("{} {} expects a number, found {}", key, operator, other),
+        };
+        let range = match operator {
+            "<" => Range { lt: Some(bound), ..Range::default() },
+            "<=" => Range { lte: Some(bound), ..Range::default() },
+            ">" => Range { gt: Some(bound), ..Range::default() },
+            _ => Range { gte: Some(bound), ..Range::default() },
+        };
+        Ok(Condition::range(key, range))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::String(text)) => Ok(Value::String(text)),
+            Some(Token::Number(number)) => Ok(Value::Number(number)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("true") => Ok(Value::Bool(true)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("false") => Ok(Value::Bool(false)),
+            Some(token) => anyhow::bail!("Expected a quoted string, number, true or false, found {:?}", token),
+            None => anyhow::bail!("Expected a value, found end of filter"),
+        }
+    }
+}
+
+/// Parses a whole filter expression into a single condition.
+pub(super) fn parse(input: &str) -> Result<Condition> {
+    let mut parser = Parser { tokens: tokenize(input)?, position: 0 };
+    anyhow::ensure!(parser.peek().is_some(), "Empty filter");
+    let condition = parser.or()?;
+    if let Some(token) = parser.peek() {
+        anyhow::bail!("Unexpected {:?} after the end of the filter", token);
+    }
+    Ok(condition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn must(conditions: Vec<Condition>) -> Condition {
+        Condition::Filter(Filter { must: conditions, ..Filter::default() })
+    }
+
+    fn should(conditions: Vec<Condition>) -> Condition {
+        Condition::Filter(Filter { should: conditions, ..Filter::default() })
+    }
+
+    fn not(condition: Condition) -> Condition {
+        Condition::Filter(Filter::default().must_not(condition))
+    }
+
+    fn error(input: &str) -> String {
+        parse(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn binds_not_tighter_than_and_and_and_tighter_than_or() {
+        let (a, b, c) = (Condition::equals("a", 1), Condition::equals("b", 2), Condition::equals("c", 3));
+        assert_eq!(parse("a = 1 OR b = 2 AND c = 3").unwrap(), should(vec![a.clone(), must(vec![b.clone(), c.clone()])]));
+        assert_eq!(parse("a = 1 AND b = 2 OR c = 3").unwrap(), should(vec![must(vec![a.clone(), b.clone()]), c.clone()]));
+        assert_eq!(parse("NOT a = 1 AND b = 2").unwrap(), must(vec![not(a.clone()), b.clone()]));
+        assert_eq!(parse("not not a = 1").unwrap(), not(not(a.clone())));
+        assert_eq!(parse("a = 1 or b = 2 or c = 3").unwrap(), should(vec![a, b, c]));
+    }
+
+    #[test]
+    fn groups_with_parentheses() {
+        let (a, b, c) = (Condition::equals("a", 1), Condition::equals("b", 2), Condition::equals("c", 3));
+        assert_eq!(parse("(a = 1 OR b = 2) AND c = 3").unwrap(), must(vec![should(vec![a.clone(), b.clone()]), c.clone()]));
+        assert_eq!(parse("NOT (a = 1 OR b = 2)").unwrap(), not(should(vec![a.clone(), b])));
+        assert_eq!(parse("((a = 1))").unwrap(), a);
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        assert_eq!(parse("year != 2023").unwrap(), not(Condition::equals("year", 2023)));
+        assert_eq!(parse("year >= 2023").unwrap(), Condition::range("year", Range { gte: Some(2023.0), ..Range::default() }));
+        assert_eq!(parse("score < -0.5").unwrap(), Condition::range("score", Range { lt: Some(-0.5), ..Range::default() }));
+        assert_eq!(parse("meta.draft = FALSE").unwrap(), Condition::equals("meta.draft", false));
+        assert_eq!(
+            parse(r#"tags IN ("faq", 'policy', 3)"#).unwrap(),
+            Condition::any("tags", vec![json!("faq"), json!("policy"), json!(3)])
+        );
+        assert_eq!(parse("sections[].title is empty").unwrap(), Condition::is_empty("sections[].title"));
+        assert_eq!(parse("section IS NOT EMPTY").unwrap(), not(Condition::is_empty("section")));
+    }
+
+    #[test]
+    fn turns_fractional_equality_into_a_range() {
+        let exactly = Range { gte: Some(1.5), lte: Some(1.5), ..Range::default() };
+        assert_eq!(parse("price = 1.5").unwrap(), Condition::range("price", exactly));
+        assert_eq!(
+            parse("price IN (1.5, 2)").unwrap(),
+            should(vec![Condition::range("price", exactly), Condition::equals("price", 2)])
+        );
+        assert_eq!(parse("price = 2").unwrap(), Condition::equals("price", 2));
+    }
+
+    #[test]
+    fn unquotes_strings() {
+        assert_eq!(parse(r#"title = "say \"hi\"""#).unwrap(), Condition::equals("title", r#"say "hi""#));
+        assert_eq!(parse(r"title = 'it\'s'").unwrap(), Condition::equals("title", "it's"));
+        assert_eq!(parse(r#"title = 'a "b" (c) AND d'"#).unwrap(), Condition::equals("title", r#"a "b" (c) AND d"#));
+        assert_eq!(parse("title = 'naïve'").unwrap(), Condition::equals("title", "naïve"));
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        assert_eq!(error("a ~ 1"), "Unexpected '~' at position 2");
+        assert_eq!(error("a = 1 AND b ! 2"), "Expected != at position 12");
+        assert_eq!(error(r#"a = 1 OR b = "open"#), "Unterminated string starting at position 13");
+        assert_eq!(error("a = 1.2.3"), "Invalid number 1.2.3");
+        assert_eq!(error("a = 1 b = 2"), r#"Unexpected Word("b") after the end of the filter"#);
+        assert_eq!(error("(a = 1"), "Expected Close, found end of filter");
+        assert_eq!(error("a = 1 AND"), "Expected a payload key, found end of filter");
+        assert_eq!(error("a < 'x'"), "a < expects a number, found \"x\"");
+        assert_eq!(error("a IS FULL"), "Expected EMPTY after a IS");
+        assert_eq!(error("a = b"), r#"Expected a quoted string, number, true or false, found Word("b")"#);
+        assert_eq!(error("   "), "Empty filter");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::store::Distance;
+
+/// Distance between two vectors under `metric`, where smaller always means more similar.
+///
+/// Cosine vectors are normalized on insert, so cosine distance reduces to a dot product.
+pub fn distance(metric: Distance, a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len(), "vectors of different dimensions");
+    match metric {
+        Distance::Cosine => 1.0 - dot(a, b),
+        Distance::Dot => -dot(a, b),
+        Distance::Euclid => a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt(),
+        Distance::Manhattan => a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum(),
+    }
+}
+
+/// Converts a [`distance`] into the score Qdrant reports for `metric`: the similarity for
+/// cosine and dot, where higher is better, and the distance itself for euclid and manhattan.
+pub fn score(metric: Distance, distance: f32) -> f32 {
+    match metric {
+        Distance::Cosine => 1.0 - distance,
+        Distance::Dot => -distance,
+        Distance::Euclid | Distance::Manhattan => distance,
+    }
+}
+
+/// Whether `score` is at least as good as `threshold` under `metric`.
+pub fn within_threshold(metric: Distance, score: f32, threshold: f32) -> bool {
+    match metric {
+        Distance::Cosine | Distance::Dot => score >= threshold,
+        Distance::Euclid | Distance::Manhattan => score <= threshold,
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len(), "vectors of different dimensions");
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Scales `vector` to unit length; the zero vector is left alone.
+pub fn normalize(vector: &mut [f32]) {
+    let norm = dot(vector, vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::str::FromStr;
+
+pub mod embedded;
+mod filter;
+mod metric;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod types;
+
+pub use embedded::{EmbeddedStore, IndexKind};
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use filter::{Condition, FieldCondition, FieldKey, Filter, MatchValue, Range};
+pub use types::*;
+
+/// Which [`VectorStore`] implementation to use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A Qdrant server.
+    #[default]
+    Qdrant,
+    /// The in-process [`EmbeddedStore`], persisted to a local directory.
+    Embedded,
+    /// PostgreSQL with the pgvector extension. Requires the `postgres` feature.
+    Postgres,
+    /// A single SQLite file. Requires the `sqlite` feature.
+    Sqlite,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "qdrant" => Ok(Backend::Qdrant),
+            "embedded" => Ok(Backend::Embedded),
+            "postgres" | "postgresql" | "pgvector" => Ok(Backend::Postgres),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => anyhow::bail!("Unknown store {}, expected qdrant, embedded, postgres or sqlite", s),
+        }
+    }
+}
+
+/// A database that stores vectors with JSON payloads in named collections and finds the
+/// nearest ones to a query.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Creates a collection, replacing nothing: creating one that exists is an error.
+    async fn create_collection(&self, collection: &Collection) -> Result<()>;
+
+    /// Deletes a collection with all its points.
+    async fn drop_collection(&self, name: &str) -> Result<()>;
+
+    /// Inserts points, replacing those whose ids already exist.
+    async fn upsert(&self, collection: &str, points: Point) -> Result<()>;
+
+    /// Removes points by id; ids that do not exist are ignored.
+    async fn delete(&self, collection: &str, ids: &[PointId]) -> Result<()>;
+
+    /// Returns the points closest to `request.vector` that match `request.filter`, best first.
+    async fn search(&self, collection: &str, request: SearchRequest) -> Result<Vec<SearchResultItem>>;
+
+    /// Exact number of points in the collection.
+    async fn count(&self, collection: &str) -> Result<u64>;
+
+    /// Pages through all points in id order, starting at `offset`.
+    async fn scroll(&self, collection: &str, limit: usize, offset: Option<PointId>) -> Result<ScrollPage>;
+
+    /// Names of all collections.
+    async fn list_collections(&self) -> Result<Vec<String>>;
+
+    /// Persists writes the store buffers in memory; stores that write through do nothing.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
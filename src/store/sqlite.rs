@@ -0,0 +1,448 @@
+//! Single-file store on SQLite.
+//!
+//! Vectors are little-endian `f32` blobs and payloads are JSON text, so filters run in SQL
+//! through `json_each` while the nearest-neighbour search scans the matching rows. That is
+//! exact and fast enough for corpora of up to a few hundred thousand points.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rusqlite::{
+    params, params_from_iter,
+    types::{Value, ValueRef},
+    Connection, OptionalExtension, ToSql,
+};
+use std::{fs, path::Path, sync::Mutex};
+
+use super::{
+    filter::{self, key_segments}, metric, Collection, Condition, Distance, FieldCondition, Filter, MatchValue, Point, PointId,
+    Record, ScrollPage, SearchRequest, SearchResultItem, VectorStore,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS collections (
+        name TEXT PRIMARY KEY,
+        vector_size INTEGER NOT NULL,
+        distance TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS points (
+        collection TEXT NOT NULL,
+        id INTEGER NOT NULL,
+        vector BLOB NOT NULL,
+        payload TEXT NOT NULL DEFAULT '{}',
+        PRIMARY KEY (collection, id)
+    ) WITHOUT ROWID;
+";
+
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database file at `path`, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let connection = Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+}
+
+/// Size and distance of a collection.
+fn collection_config(connection: &Connection, name: &str) -> Result<Collection> {
+    connection
+        .query_row(
+            "SELECT vector_size, distance FROM collections WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+        .with_context(|| format!("Collection {} does not exist", name))
+        .and_then(|(vector_size, distance)| Ok(Collection {
+            name: name.to_string(),
+            vector_size: vector_size as usize,
+            distance: distance.parse()?,
+        }))
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(4).map(|value| f32::from_le_bytes(value.try_into().unwrap())).collect()
+}
+
+/// Numeric ids are stored as integers and UUIDs as text in the same column; SQLite sorts all
+/// integers before all text, the same order as [`PointId`].
+fn sql_id(id: PointId) -> Result<Value> {
+    match id {
+        PointId::Num(id) => Ok(Value::Integer(
+            i64::try_from(id).with_context(|| format!("Point id {} does not fit in a SQLite integer", id))?
+        )),
+        PointId::Uuid(id) => Ok(Value::Text(id.to_string())),
+    }
+}
+
+fn read_id(value: ValueRef) -> Result<PointId> {
+    match value {
+        ValueRef::Integer(id) => Ok(PointId::Num(id as u64)),
+        ValueRef::Text(id) => std::str::from_utf8(id)?.parse(),
+        _ => anyhow::bail!("Point id column holds neither an integer nor text"),
+    }
+}
+
+fn parse_payload(text: &str) -> Result<serde_json::Map<String, serde_json::Value>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// [`filter::filter_sql`] with the SQLite conditions below.
+fn filter_sql(filter: &Filter, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    filter::filter_sql(filter, params, "1", condition_sql)
+}
+
+/// A condition holds if any value of its key passes `value_sql`, so it becomes an `EXISTS` over
+/// the `json_each` rows of the key. Both sides of an equality go through `json_extract` so that
+/// JSON strings, numbers and booleans compare the same way SQLite stores them.
+fn condition_sql(condition: &Condition, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    match condition {
+        Condition::IsEmpty { is_empty } => {
+            let (from, value) = key_values(&is_empty.key, params);
+            format!("NOT EXISTS (SELECT 1 FROM {} WHERE {}.type != 'null')", from, value)
+        }
+        Condition::Field(FieldCondition { key, matches, range }) => {
+            let (from, value) = key_values(key, params);
+            let mut tests = Vec::new();
+            match matches {
+                Some(MatchValue::Value { value: expected }) => {
+                    params.push(Box::new(expected.to_string()));
+                    tests.push(format!("{}.value = json_extract(?{}, '$')", value, params.len()));
+                }
+                Some(MatchValue::Any { any }) => {
+                    let mut placeholders = Vec::new();
+                    for expected in any {
+                        params.push(Box::new(expected.to_string()));
+                        placeholders.push(format!("json_extract(?{}, '$')", params.len()));
+                    }
+                    tests.push(format!("{}.value IN ({})", value, placeholders.join(", ")));
+                }
+                None => {}
+            }
+            if let Some(range) = range {
+                tests.push(format!("{}.type IN ('integer', 'real')", value));
+                for (operator, bound) in [(">", range.gt), (">=", range.gte), ("<", range.lt), ("<=", range.lte)] {
+                    if let Some(bound) = bound {
+                        params.push(Box::new(bound));
+                        tests.push(format!("{}.value {} ?{}", value, operator, params.len()));
+                    }
+                }
+            }
+            if tests.is_empty() {
+                tests.push("1".to_string());
+            }
+            format!("EXISTS (SELECT 1 FROM {} WHERE {})", from, tests.join(" AND "))
+        }
+        Condition::Filter(filter) => filter_sql(filter, params),
+    }
+}
+
+/// `json_each` tables yielding every value of `key`, one table per `[]` in the key plus one
+/// that flattens the array at its end, and the alias of the last one. Later tables address the
+/// elements of earlier ones by their `fullkey`, so elements that are not objects yield nothing.
+fn key_values(key: &str, params: &mut Vec<Box<dyn ToSql>>) -> (String, String) {
+    let mut tables: Vec<String> = Vec::new();
+    let mut path = "$".to_string();
+    let mut table = |path: &mut String, tables: &mut Vec<String>| {
+        params.push(Box::new(std::mem::take(path)));
+        let root = match tables.len() {
+            0 => String::new(),
+            previous => format!("j{}.fullkey || ", previous - 1),
+        };
+        tables.push(format!("json_each(points.payload, {}?{}) AS j{}", root, params.len(), tables.len()));
+    };
+    for (name, array) in key_segments(key) {
+        path += &format!(".\"{}\"", name.replace('"', "\\\""));
+        if array {
+            table(&mut path, &mut tables);
+        }
+    }
+    table(&mut path, &mut tables);
+    let value = format!("j{}", tables.len() - 1);
+    (tables.join(", "), value)
+}
+
+#[async_trait]
+impl VectorStore for SqliteStore {
+    async fn create_collection(&self, collection: &Collection) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        let inserted = connection.execute(
+            "INSERT OR IGNORE INTO collections (name, vector_size, distance) VALUES (?1, ?2, ?3)",
+            params![collection.name, collection.vector_size as i64, collection.distance.as_str()],
+        )?;
+        anyhow::ensure!(inserted == 1, "Collection {} already exists", collection.name);
+        Ok(())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let deleted = transaction.execute("DELETE FROM collections WHERE name = ?1", params![name])?;
+        anyhow::ensure!(deleted == 1, "Collection {} does not exist", name);
+        transaction.execute("DELETE FROM points WHERE collection = ?1", params![name])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, points: Point) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let config = collection_config(&connection, collection)?;
+        points.check(collection, config.vector_size)?;
+
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO points (collection, id, vector, payload) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (collection, id) DO UPDATE SET vector = excluded.vector, payload = excluded.payload",
+            )?;
+            let mut payloads = points.payloads.unwrap_or_default().into_iter();
+            for (id, mut vector) in points.ids.into_iter().zip(points.vectors) {
+                if config.distance == Distance::Cosine {
+                    metric::normalize(&mut vector);
+                }
+                let payload = serde_json::to_string(&payloads.next().unwrap_or_default())?;
+                statement.execute(params![collection, sql_id(id)?, encode_vector(&vector), payload])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[PointId]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached("DELETE FROM points WHERE collection = ?1 AND id = ?2")?;
+            for &id in ids {
+                statement.execute(params![collection, sql_id(id)?])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn search(&self, collection: &str, request: SearchRequest) -> Result<Vec<SearchResultItem>> {
+        let connection = self.connection.lock().unwrap();
+        let config = collection_config(&connection, collection)?;
+        anyhow::ensure!(
+            request.vector.len() == config.vector_size,
+            "Query has {} dimensions, collection {} expects {}", request.vector.len(), collection, config.vector_size
+        );
+
+        let mut query = request.vector;
+        if config.distance == Distance::Cosine {
+            metric::normalize(&mut query);
+        }
+
+        let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(collection.to_string())];
+        let mut sql = "SELECT id, vector, payload FROM points WHERE collection = ?1".to_string();
+        if let Some(filter) = &request.filter {
+            sql += &format!(" AND {}", filter_sql(filter, &mut params));
+        }
+
+        let mut statement = connection.prepare(&sql)?;
+        let mut rows = statement.query(params_from_iter(params.iter()))?;
+        let mut found = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id = read_id(row.get_ref(0)?)?;
+            let vector = decode_vector(row.get_ref(1)?.as_blob()?);
+            let payload: String = row.get(2)?;
+            let distance = metric::distance(config.distance, &query, &vector);
+            found.push((id, distance, payload, request.with_vectors.then_some(vector)));
+        }
+
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.truncate(request.offset + request.limit);
+        found.into_iter()
+            .skip(request.offset)
+            .map(|(id, distance, payload, vector)| (id, metric::score(config.distance, distance), payload, vector))
+            .filter(|&(_, score, _, _)| {
+                request.score_threshold.is_none_or(|threshold| metric::within_threshold(config.distance, score, threshold))
+            })
+            .map(|(id, score, payload, vector)| Ok(SearchResultItem {
+                id,
+                score,
+                payload: Some(parse_payload(&payload)?),
+                vector: vector.map(|vector| serde_json::json!(vector)),
+            }))
+            .collect()
+    }
+
+    async fn count(&self, collection: &str) -> Result<u64> {
+        let connection = self.connection.lock().unwrap();
+        collection_config(&connection, collection)?;
+        let count: i64 = connection.query_row(
+            "SELECT count(*) FROM points WHERE collection = ?1",
+            params![collection],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    async fn scroll(&self, collection: &str, limit: usize, offset: Option<PointId>) -> Result<ScrollPage> {
+        let connection = self.connection.lock().unwrap();
+        collection_config(&connection, collection)?;
+
+        // One row more than asked for tells whether there is a next page, and where it starts.
+        let offset = offset.map(sql_id).transpose()?.unwrap_or(Value::Null);
+        let mut statement = connection.prepare(
+            "SELECT id, payload FROM points WHERE collection = ?1 AND (?2 IS NULL OR id >= ?2) ORDER BY id LIMIT ?3",
+        )?;
+        let mut rows = statement.query(params![collection, offset, limit as i64 + 1])?;
+
+        let mut points = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id = read_id(row.get_ref(0)?)?;
+            let payload = parse_payload(row.get_ref(1)?.as_str()?)?;
+            points.push(Record { id, payload: Some(payload), vector: None });
+        }
+        let next_page_offset = if points.len() > limit { points.pop().map(|point| point.id) } else { None };
+        Ok(ScrollPage { points, next_page_offset })
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT name FROM collections ORDER BY name")?;
+        let names = statement.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    /// A store in a fresh database file with the collection `name`.
+    async fn store(name: &str, distance: Distance) -> (tempfile::TempDir, SqliteStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::open(dir.path().join("store.sqlite")).unwrap();
+        store.create_collection(&Collection { name: name.to_string(), vector_size: 2, distance }).await.unwrap();
+        (dir, store)
+    }
+
+    fn payload(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn points(vectors: Vec<Vec<f32>>) -> Point {
+        Point { ids: (1..=vectors.len() as u64).map(PointId::Num).collect(), vectors, payloads: None }
+    }
+
+    fn ids(hits: &[SearchResultItem]) -> Vec<PointId> {
+        hits.iter().map(|hit| hit.id).collect()
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_points() {
+        let (_dir, store) = store("docs", Distance::Dot).await;
+
+        let mut extra_id = points(vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        extra_id.vectors.pop();
+        assert!(store.upsert("docs", extra_id).await.is_err());
+        let mut missing_payload = points(vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        missing_payload.payloads = Some(vec![HashMap::new()]);
+        assert!(store.upsert("docs", missing_payload).await.is_err());
+        let mut wrong_size = points(vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        wrong_size.vectors[1].push(0.0);
+        assert!(store.upsert("docs", wrong_size).await.is_err());
+        assert_eq!(store.count("docs").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn normalizes_cosine_vectors() {
+        let (_dir, store) = store("docs", Distance::Cosine).await;
+        store.upsert("docs", points(vec![vec![3.0, 4.0]])).await.unwrap();
+
+        let request = SearchRequest { with_vectors: true, ..SearchRequest::new(vec![6.0, 8.0], 1) };
+        let hits = store.search("docs", request).await.unwrap();
+
+        assert!((hits[0].score - 1.0).abs() < 1e-6, "{}", hits[0].score);
+        assert_eq!(hits[0].vector, Some(serde_json::json!([0.6f32, 0.8f32])));
+    }
+
+    #[tokio::test]
+    async fn applies_offset_and_score_threshold() {
+        let (_dir, store) = store("cosine", Distance::Cosine).await;
+        store.upsert("cosine", points(vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]])).await.unwrap();
+        let query = || SearchRequest::new(vec![1.0, 0.0], 3);
+
+        let skipped = store.search("cosine", SearchRequest { offset: 1, ..query() }).await.unwrap();
+        assert_eq!(ids(&skipped), [PointId::Num(2), PointId::Num(3)]);
+        let similar = store.search("cosine", SearchRequest { score_threshold: Some(0.5), ..query() }).await.unwrap();
+        assert_eq!(ids(&similar), [PointId::Num(1), PointId::Num(2)]);
+
+        store.create_collection(&Collection { name: "euclid".to_string(), vector_size: 2, distance: Distance::Euclid }).await.unwrap();
+        store.upsert("euclid", points(vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![3.0, 0.0]])).await.unwrap();
+        let close = store.search("euclid", SearchRequest { score_threshold: Some(1.5), ..SearchRequest::new(vec![0.0, 0.0], 3) }).await.unwrap();
+        assert_eq!(ids(&close), [PointId::Num(1), PointId::Num(2)]);
+    }
+
+    #[tokio::test]
+    async fn scrolls_numeric_ids_before_uuids() {
+        let (_dir, store) = store("docs", Distance::Dot).await;
+        let low = PointId::Uuid(Uuid::parse_str("0a8f2c4e-1111-4c3b-9d2e-000000000001").unwrap());
+        let high = PointId::Uuid(Uuid::parse_str("f1e2d3c4-2222-4c3b-9d2e-000000000002").unwrap());
+        let mut all = vec![PointId::Num(10), high, PointId::Num(2), low, PointId::Num(7)];
+        store.upsert("docs", Point { ids: all.clone(), vectors: vec![vec![1.0, 0.0]; 5], payloads: None }).await.unwrap();
+
+        let mut scrolled = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = store.scroll("docs", 2, offset).await.unwrap();
+            assert!(page.points.len() <= 2);
+            scrolled.extend(page.points.iter().map(|point| point.id));
+            offset = page.next_page_offset;
+            if offset.is_none() {
+                break;
+            }
+        }
+
+        all.sort();
+        assert_eq!(scrolled, all);
+        assert_eq!(scrolled[..3], [PointId::Num(2), PointId::Num(7), PointId::Num(10)]);
+    }
+
+    /// Ids of the points of `name` matching `filter`, in order.
+    async fn matching(store: &SqliteStore, name: &str, filter: &str) -> Vec<PointId> {
+        let mut request = SearchRequest::new(vec![1.0, 1.0], 10);
+        request.filter = Some(filter.parse().unwrap());
+        let mut ids = ids(&store.search(name, request).await.unwrap());
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn filters_with_json_paths() {
+        let (_dir, store) = store("docs", Distance::Cosine).await;
+        store.upsert("docs", Point {
+            ids: vec![PointId::Num(1), PointId::Num(2), PointId::Num(3)],
+            vectors: vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]],
+            payloads: Some(vec![
+                payload(serde_json::json!({ "source": "a.txt", "meta": { "year": 2021 }, "tags": ["faq"], "sections": [{ "title": "Scope" }] })),
+                payload(serde_json::json!({ "source": "b.txt", "meta": { "year": 2024 }, "tags": ["policy", "faq"] })),
+                payload(serde_json::json!({ "source": "c \"quoted\".txt", "tags": [] })),
+            ]),
+        }).await.unwrap();
+
+        assert_eq!(matching(&store, "docs", r#"source = "b.txt""#).await, [PointId::Num(2)]);
+        assert_eq!(matching(&store, "docs", r#"source = 'c "quoted".txt'"#).await, [PointId::Num(3)]);
+        assert_eq!(matching(&store, "docs", "meta.year >= 2022").await, [PointId::Num(2)]);
+        assert_eq!(matching(&store, "docs", r#"tags = "faq" AND NOT meta.year < 2022"#).await, [PointId::Num(2)]);
+        assert_eq!(matching(&store, "docs", r#"tags IN ("policy", "none") OR sections[].title = "Scope""#).await, [PointId::Num(1), PointId::Num(2)]);
+        assert_eq!(matching(&store, "docs", "tags IS EMPTY").await, [PointId::Num(3)]);
+        assert_eq!(matching(&store, "docs", "meta.year IS NOT EMPTY").await, [PointId::Num(1), PointId::Num(2)]);
+    }
+}
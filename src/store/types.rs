@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr};
+use uuid::Uuid;
+
+use super::Filter;
+
+/// Name and vector layout of a collection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    pub vector_size: usize,
+    pub distance: Distance,
+}
+
+/// Similarity metric of a collection, named as Qdrant expects it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Distance {
+    Cosine,
+    Dot,
+    Euclid,
+    Manhattan,
+}
+
+impl Distance {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Distance::Cosine => "Cosine",
+            Distance::Dot => "Dot",
+            Distance::Euclid => "Euclid",
+            Distance::Manhattan => "Manhattan",
+        }
+    }
+}
+
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Distance {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "cosine" => Ok(Distance::Cosine),
+            "dot" => Ok(Distance::Dot),
+            "euclid" | "euclidean" => Ok(Distance::Euclid),
+            "manhattan" => Ok(Distance::Manhattan),
+            _ => anyhow::bail!("Unknown distance {}, expected cosine, dot, euclid or manhattan", s),
+        }
+    }
+}
+
+/// Id of a point: an unsigned integer or a UUID, the two kinds Qdrant accepts.
+///
+/// Serializes as a bare number or a UUID string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PointId {
+    Num(u64),
+    Uuid(Uuid),
+}
+
+impl From<u64> for PointId {
+    fn from(id: u64) -> Self {
+        PointId::Num(id)
+    }
+}
+
+impl From<Uuid> for PointId {
+    fn from(id: Uuid) -> Self {
+        PointId::Uuid(id)
+    }
+}
+
+impl fmt::Display for PointId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointId::Num(id) => write!(f, "{}", id),
+            PointId::Uuid(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl FromStr for PointId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if let Ok(id) = s.parse::<u64>() {
+            return Ok(PointId::Num(id));
+        }
+        Uuid::parse_str(s)
+            .map(PointId::Uuid)
+            .map_err(|_| anyhow::anyhow!("Invalid point id {}, expected an unsigned integer or a UUID", s))
+    }
+}
+
+/// A batch of points in column form: `payloads`, when present, holds one entry per id.
+#[derive(Serialize, Deserialize)]
+pub struct Point {
+    pub ids: Vec<PointId>,
+    pub vectors: Vec<Vec<f32>>,
+    pub payloads: Option<Vec<HashMap<String, serde_json::Value>>>,
+}
+
+impl Point {
+    /// Checks that the batch has one vector (and payload, if any) per id and that every vector
+    /// has the `vector_size` of `collection`.
+    pub(crate) fn check(&self, collection: &str, vector_size: usize) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.ids.len() == self.vectors.len(),
+            "Got {} point ids but {} vectors", self.ids.len(), self.vectors.len()
+        );
+        if let Some(payloads) = &self.payloads {
+            anyhow::ensure!(
+                payloads.len() == self.ids.len(),
+                "Got {} point ids but {} payloads", self.ids.len(), payloads.len()
+            );
+        }
+        for (id, vector) in self.ids.iter().zip(&self.vectors) {
+            anyhow::ensure!(
+                vector.len() == vector_size,
+                "Point {} has {} dimensions, collection {} expects {}", id, vector.len(), collection, vector_size
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A query for the nearest neighbours of `vector`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub vector: Vec<f32>,
+    /// Maximum number of hits.
+    pub limit: usize,
+    /// Number of best hits to skip, for paging through results.
+    #[serde(default)]
+    pub offset: usize,
+    /// Drop hits scoring worse than this: below it for cosine and dot, above it for euclid
+    /// and manhattan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    /// Return the stored vector of every hit.
+    #[serde(default)]
+    pub with_vectors: bool,
+}
+
+impl SearchRequest {
+    /// The `limit` nearest neighbours of `vector`, without filter, offset or threshold.
+    pub fn new(vector: Vec<f32>, limit: usize) -> Self {
+        Self { vector, limit, offset: 0, score_threshold: None, filter: None, with_vectors: false }
+    }
+}
+
+/// A stored point.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub id: PointId,
+    #[serde(default)]
+    pub payload: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub vector: Option<serde_json::Value>,
+}
+
+/// One page of [`VectorStore::scroll`](super::VectorStore::scroll).
+#[derive(Debug, Deserialize)]
+pub struct ScrollPage {
+    pub points: Vec<Record>,
+    /// Offset of the next page, or `None` after the last one.
+    pub next_page_offset: Option<PointId>,
+}
+
+/// A search hit.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResultItem {
+    pub id: PointId,
+    /// Similarity to the query as Qdrant reports it: higher is better for cosine and dot,
+    /// lower is better for euclid and manhattan.
+    pub score: f32,
+    #[serde(default)]
+    pub payload: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Stored vector, when requested with [`SearchRequest::with_vectors`].
+    #[serde(default)]
+    pub vector: Option<serde_json::Value>,
+}
@@ -0,0 +1,73 @@
+//! Helpers shared by the unit tests.
+
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A request received by [`mock_server`].
+pub struct Recorded {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    /// The JSON body, or `Null` if the request had none.
+    pub body: serde_json::Value,
+}
+
+/// Serves `responses` as `(status, extra headers, body)`, one connection each, recording
+/// every request it receives.
+pub async fn mock_server(responses: Vec<(u16, &'static str, String)>) -> (String, Arc<Mutex<Vec<Recorded>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&requests);
+    tokio::spawn(async move {
+        for (status, headers, body) in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await;
+            recorded.lock().unwrap().push(request);
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                status, body.len(), headers, body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+async fn read_request(stream: &mut TcpStream) -> Recorded {
+    let mut buffer = Vec::new();
+    let head_end = loop {
+        let mut chunk = [0; 4096];
+        let read = stream.read(&mut chunk).await.unwrap();
+        assert!(read > 0, "connection closed before the request head ended");
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+    let head = String::from_utf8(buffer[..head_end].to_vec()).unwrap();
+    let header = |name: &str| head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string());
+    let length: usize = header("content-length").map_or(0, |length| length.parse().unwrap());
+    while buffer.len() < head_end + length {
+        let mut chunk = [0; 4096];
+        let read = stream.read(&mut chunk).await.unwrap();
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let mut request_line = head.split_whitespace();
+    Recorded {
+        method: request_line.next().unwrap().to_string(),
+        path: request_line.next().unwrap().to_string(),
+        authorization: header("authorization"),
+        body: match length {
+            0 => serde_json::Value::Null,
+            _ => serde_json::from_slice(&buffer[head_end..head_end + length]).unwrap(),
+        },
+    }
+}